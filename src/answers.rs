@@ -0,0 +1,70 @@
+use std::{fs, path::Path};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::ScafalraError, json::JsonContent};
+
+/// Records how a project was created, so that it can be upgraded later
+#[derive(Deserialize, Serialize, Default, Debug, PartialEq)]
+pub struct Answers {
+	pub template: String,
+	pub url: String,
+	/// Path of the template in the repository
+	pub subdir: String,
+	pub commit: Option<String>,
+	pub sub_templates: Vec<String>,
+}
+
+impl JsonContent for Answers {}
+
+impl Answers {
+	pub const FILE_NAME: &'static str = ".scafalra-answers";
+
+	pub fn read(project_dir: &Path) -> Result<Self> {
+		let file_path = project_dir.join(Self::FILE_NAME);
+
+		if !file_path.is_file() {
+			anyhow::bail!(ScafalraError::NotFound(format!(
+				"`{}` was not found, the project may not be created by scafalra",
+				file_path.to_string_lossy()
+			)));
+		}
+
+		let content = fs::read_to_string(&file_path)?;
+
+		Ok(serde_json::from_str(&content)?)
+	}
+
+	pub fn write(&self, project_dir: &Path) -> Result<()> {
+		self.save(&project_dir.join(Self::FILE_NAME))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use anyhow::Result;
+	use tempfile::tempdir;
+
+	use super::Answers;
+
+	#[test]
+	fn test_answers_read_write() -> Result<()> {
+		let tmp_dir = tempdir()?;
+
+		assert!(Answers::read(tmp_dir.path()).is_err());
+
+		let answers = Answers {
+			template: "bar".to_string(),
+			url: "url".to_string(),
+			subdir: "a/a1".to_string(),
+			commit: Some("commit".to_string()),
+			sub_templates: vec!["dir-1".to_string()],
+		};
+		answers.write(tmp_dir.path())?;
+
+		assert_eq!(Answers::read(tmp_dir.path())?, answers);
+
+		Ok(())
+	}
+}
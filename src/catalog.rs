@@ -0,0 +1,206 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tabled::{
+	settings::{format::Format, object::Segment, Alignment, Modify, Style},
+	Table, Tabled,
+};
+
+use crate::{error::ScafalraError, source::Source, store::Suggestion};
+
+/// A template listed in a catalog
+#[derive(Deserialize, Serialize, Clone, Debug, Tabled)]
+pub struct CatalogEntry {
+	pub name: String,
+	#[serde(default)]
+	pub description: String,
+	#[tabled(display_with = "display_tags")]
+	#[serde(default)]
+	pub tags: Vec<String>,
+	pub source: Source,
+}
+
+fn display_tags(tags: &[String]) -> String {
+	tags.join(",")
+}
+
+/// The index file of a catalog, the indexes of all configured catalogs are
+/// merged into one
+#[derive(Deserialize, Serialize, Default)]
+pub struct Catalog {
+	#[serde(default)]
+	pub templates: Vec<CatalogEntry>,
+}
+
+impl Catalog {
+	/// The lowest score of a search result, the same as the one of
+	/// `Store::similar_name_suggestion`
+	const MIN_SCORE: f64 = 0.5;
+
+	/// `origin` is the file or url the content is read from
+	pub fn parse(content: &str, origin: &str) -> Result<Self> {
+		let catalog = serde_json::from_str(content).map_err(|err| {
+			ScafalraError::Usage(format!(
+				"Failed to parse catalog `{}`: {}",
+				origin, err
+			))
+		})?;
+
+		Ok(catalog)
+	}
+
+	/// Entries of `other` whose name is already present are ignored
+	pub fn merge(&mut self, other: Self) {
+		for entry in other.templates {
+			if self.get(&entry.name).is_none() {
+				self.templates.push(entry);
+			}
+		}
+	}
+
+	pub fn get(&self, name: &str) -> Option<&CatalogEntry> {
+		self.templates.iter().find(|entry| entry.name == name)
+	}
+
+	/// The entries matching `query`, best matches first
+	pub fn search(&self, query: &str) -> Vec<&CatalogEntry> {
+		let mut matches = self
+			.templates
+			.iter()
+			.map(|entry| (entry, score(entry, query)))
+			.filter(|(_, score)| *score > Self::MIN_SCORE)
+			.collect::<Vec<_>>();
+
+		matches.sort_by(|x, y| {
+			y.1.total_cmp(&x.1).then_with(|| x.0.name.cmp(&y.0.name))
+		});
+
+		matches.into_iter().map(|(entry, _)| entry).collect()
+	}
+
+	pub fn similar_name_suggestion<'a>(
+		&'a self,
+		target: &'a str,
+	) -> Suggestion<'a> {
+		Suggestion {
+			noun: "template",
+			target,
+			similar: self.search(target).first().map(|e| e.name.as_str()),
+		}
+	}
+}
+
+/// Name and tags are compared with `normalized_levenshtein`, containing the
+/// query also counts as a match
+fn score(entry: &CatalogEntry, query: &str) -> f64 {
+	use strsim::normalized_levenshtein;
+
+	let query = query.to_lowercase();
+	let name = entry.name.to_lowercase();
+
+	let mut score = std::iter::once(&name)
+		.chain(&entry.tags)
+		.map(|word| normalized_levenshtein(&query, &word.to_lowercase()))
+		.fold(0.0, f64::max);
+
+	if name.contains(&query) {
+		score = score.max(0.9);
+	} else if entry
+		.tags
+		.iter()
+		.any(|tag| tag.to_lowercase().contains(&query))
+	{
+		score = score.max(0.8);
+	} else if entry.description.to_lowercase().contains(&query) {
+		score = score.max(0.6);
+	}
+
+	score
+}
+
+pub fn print_entries(entries: &[&CatalogEntry]) -> Option<String> {
+	use crate::colorize::Colorize;
+
+	if entries.is_empty() {
+		return None;
+	}
+
+	let mut table = Table::new(entries.iter().copied());
+
+	let modify =
+		Modify::new(Segment::new(1.., ..1)).with(Format::content(|s| s.blue()));
+
+	table
+		.with(Style::psql())
+		.with(Alignment::left())
+		.with(modify);
+
+	Some(table.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Catalog;
+
+	fn catalog() -> Catalog {
+		Catalog::parse(
+			r#"{
+  "templates": [
+    {
+      "name": "react-app",
+      "description": "A React application",
+      "tags": ["web", "frontend"],
+      "source": { "repo": "foo/react-app" }
+    },
+    {
+      "name": "axum-server",
+      "description": "An HTTP server with axum",
+      "tags": ["rust", "backend"],
+      "source": { "repo": "foo/templates", "subdir": "/axum" }
+    }
+  ]
+}"#,
+			"catalog.json",
+		)
+		.unwrap()
+	}
+
+	#[test]
+	fn test_catalog_search() {
+		let catalog = catalog();
+		let names = |query| {
+			catalog
+				.search(query)
+				.into_iter()
+				.map(|entry| entry.name.as_str())
+				.collect::<Vec<_>>()
+		};
+
+		assert_eq!(names("react"), vec!["react-app"]);
+		assert_eq!(names("raect-app"), vec!["react-app"]);
+		assert_eq!(names("rust"), vec!["axum-server"]);
+		assert_eq!(names("http"), vec!["axum-server"]);
+		assert!(names("foo").is_empty());
+	}
+
+	#[test]
+	fn test_catalog_merge() {
+		let mut catalog = catalog();
+		catalog.merge(
+			super::Catalog::parse(
+				r#"{"templates": [
+				{"name": "react-app", "source": {"repo": "bar/react-app"}},
+				{"name": "vue-app", "source": {"repo": "bar/vue-app"}}
+			]}"#,
+				"other.json",
+			)
+			.unwrap(),
+		);
+
+		assert_eq!(catalog.templates.len(), 3);
+		assert_eq!(
+			catalog.get("react-app").unwrap().source.repo,
+			"foo/react-app"
+		);
+		assert!(Catalog::parse(r#"{"templates": 1}"#, "bad.json").is_err());
+	}
+}
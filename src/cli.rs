@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 
-use clap::{builder::PossibleValuesParser, Args, Parser, Subcommand};
+use clap::{
+	builder::PossibleValuesParser, Args, Parser, Subcommand, ValueEnum,
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -12,11 +14,24 @@ pub struct Cli {
 	#[arg(long, global = true)]
 	pub debug: bool,
 
+	/// Do not display progress bars
+	#[arg(short, long, global = true)]
+	pub quiet: bool,
+
+	/// Never access the network, only cached templates and local paths can
+	/// be used
+	#[arg(long, global = true)]
+	pub offline: bool,
+
 	/// Specify the GitHub personal access token
 	#[arg(long, global = true)]
 	pub token: Option<String>,
 
-	/// Display of scafalra's data storage location
+	/// Use a single directory for config, data and cache
+	#[arg(long, global = true, env = "SCAFALRA_HOME")]
+	pub home: Option<PathBuf>,
+
+	/// Display of scafalra's config, data and cache directories
 	#[arg(long)]
 	pub proj_dir: bool,
 
@@ -38,7 +53,7 @@ pub enum Command {
 	#[command(visible_alias = "mv")]
 	Rename(RenameArgs),
 
-	/// Add template from GitHub repository
+	/// Add template from GitHub repository, local directory or zip archive
 	Add(AddArgs),
 
 	/// Copy the template folder to the specified directory
@@ -46,6 +61,33 @@ pub enum Command {
 
 	/// Configure or display your GitHub personal access token
 	Token(TokenArgs),
+
+	/// Display the directory tree or a file's contents of a template
+	Show(ShowArgs),
+
+	/// Apply the changes of a template to a project created from it
+	Upgrade(UpgradeArgs),
+
+	/// Display the current GitHub API rate limit
+	RateLimit,
+
+	/// Check and repair the template store
+	Doctor(DoctorArgs),
+
+	/// Print the sources of all templates as JSON
+	Export,
+
+	/// Add the templates in a file written by `export`
+	Import(ImportArgs),
+
+	/// Make the templates match a registry file
+	Sync(SyncArgs),
+
+	/// Search the templates in the catalogs
+	Search(SearchArgs),
+
+	/// List all templates in the catalogs
+	Catalog,
 }
 
 #[derive(Args, Debug)]
@@ -73,7 +115,7 @@ pub struct RenameArgs {
 #[derive(Args, Debug)]
 #[cfg_attr(test, derive(Clone))]
 pub struct AddArgs {
-	/// owner/name/.../subdir
+	/// owner/name, a local directory, a zip archive or the name in a catalog
 	pub repository: String,
 
 	/// The depth to go when recursing repository
@@ -85,8 +127,8 @@ pub struct AddArgs {
     )]
 	pub depth: String,
 
-	/// Specify template name, if a subdir is provided, the last level of the
-	/// subdir will be used as the name
+	/// Specify template name, defaults to the repository name, or the last
+	/// level of the subdir if a subdir is provided
 	#[arg(long)]
 	pub name: Option<String>,
 
@@ -105,11 +147,30 @@ pub struct AddArgs {
 	/// Specify commit
 	#[arg(long, group = "ref")]
 	pub commit: Option<String>,
+
+	/// Tags to record with the template, separated by commas
+	#[arg(long, value_delimiter = ',')]
+	pub tags: Vec<String>,
+
+	/// Add the template with this name from the catalogs instead
+	#[arg(long, conflicts_with_all = ["subdir", "ref", "depth"])]
+	pub from_catalog: bool,
+}
+
+impl AddArgs {
+	pub fn repo_ref(&self) -> Option<&str> {
+		self.branch
+			.as_deref()
+			.or(self.tag.as_deref())
+			.or(self.commit.as_deref())
+	}
 }
 
 #[derive(Args, Debug)]
+#[cfg_attr(test, derive(Clone))]
 pub struct CreateArgs {
-	/// Template name
+	/// Template name, or `owner/repo[/subdir]` to create from a repository
+	/// without adding it
 	pub name: Option<String>,
 
 	/// Specified destination(defaults to the current directory)
@@ -118,6 +179,39 @@ pub struct CreateArgs {
 
 	#[arg(short, long)]
 	pub sub_templates: Option<Vec<String>>,
+
+	/// Print the files that would be created without writing anything
+	#[arg(long)]
+	pub dry_run: bool,
+
+	/// Allow creating into an existing directory
+	#[arg(long)]
+	pub merge: bool,
+
+	/// How to handle files that already exist when merging
+	#[arg(long, value_enum, default_value_t, requires = "merge")]
+	pub conflict: ConflictPolicy,
+
+	/// Branch, tag or commit when creating from `owner/repo[/subdir]`
+	#[arg(long = "ref")]
+	pub repo_ref: Option<String>,
+
+	/// Add the template created from `owner/repo[/subdir]` to the store
+	#[arg(long, conflicts_with = "dry_run")]
+	pub save: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum ConflictPolicy {
+	/// Keep the existing file
+	#[default]
+	Skip,
+	/// Replace the existing file
+	Overwrite,
+	/// Keep the existing file and write the new one with a suffix
+	KeepBoth,
+	/// Ask for each file
+	Prompt,
 }
 
 #[derive(Args, Debug)]
@@ -125,9 +219,59 @@ pub struct TokenArgs {
 	pub token: Option<String>,
 }
 
+#[derive(Args, Debug)]
+pub struct ShowArgs {
+	/// Template name
+	pub name: Option<String>,
+
+	/// Path of a file or directory inside the template
+	pub path: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct UpgradeArgs {
+	/// Project directory(defaults to the current directory)
+	#[arg(short, long)]
+	pub directory: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct DoctorArgs {
+	/// Rebuild the store from the cached templates instead of restoring the
+	/// backup
+	#[arg(long)]
+	pub rebuild: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+	/// File written by `export`
+	pub file: PathBuf,
+}
+
+#[derive(Args, Debug)]
+#[cfg_attr(test, derive(Clone))]
+pub struct SyncArgs {
+	/// Path or url of the registry file
+	#[arg(default_value = "scafalra.registry.toml")]
+	pub registry: String,
+
+	/// Remove the templates that are not in the registry
+	#[arg(long)]
+	pub prune: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct SearchArgs {
+	/// Name, tag or a word in the description
+	pub query: String,
+}
+
 #[cfg(test)]
 pub mod test_utils {
-	use super::AddArgs;
+	use std::path::Path;
+
+	use super::{AddArgs, ConflictPolicy, CreateArgs};
 
 	pub struct AddArgsMock {
 		args: AddArgs,
@@ -144,6 +288,8 @@ pub mod test_utils {
 					branch: None,
 					tag: None,
 					commit: None,
+					tags: Vec::new(),
+					from_catalog: false,
 				},
 			}
 		}
@@ -152,6 +298,12 @@ pub mod test_utils {
 			self.args.clone()
 		}
 
+		pub fn repository(&mut self, repository: &str) -> &mut Self {
+			self.args.repository = repository.to_string();
+
+			self
+		}
+
 		pub fn depth(&mut self, depth: &str) -> &mut Self {
 			self.args.depth = depth.to_string();
 
@@ -169,12 +321,93 @@ pub mod test_utils {
 
 			self
 		}
+
+		pub fn commit(&mut self, commit: &str) -> &mut Self {
+			self.args.commit = Some(commit.to_string());
+
+			self
+		}
+
+		pub fn catalog(&mut self) -> &mut Self {
+			self.args.from_catalog = true;
+
+			self
+		}
+	}
+
+	pub struct CreateArgsMock {
+		args: CreateArgs,
+	}
+
+	impl CreateArgsMock {
+		pub fn new() -> Self {
+			Self {
+				args: CreateArgs {
+					name: None,
+					destination: None,
+					sub_templates: None,
+					dry_run: false,
+					merge: false,
+					conflict: ConflictPolicy::Skip,
+					repo_ref: None,
+					save: false,
+				},
+			}
+		}
+
+		pub fn build(&self) -> CreateArgs {
+			self.args.clone()
+		}
+
+		pub fn name(&mut self, name: &str) -> &mut Self {
+			self.args.name = Some(name.to_string());
+
+			self
+		}
+
+		pub fn destination(&mut self, destination: &Path) -> &mut Self {
+			self.args.destination = Some(destination.to_path_buf());
+
+			self
+		}
+
+		pub fn sub_templates(&mut self, sub_templates: &[&str]) -> &mut Self {
+			self.args.sub_templates =
+				Some(sub_templates.iter().map(|s| s.to_string()).collect());
+
+			self
+		}
+
+		pub fn dry_run(&mut self) -> &mut Self {
+			self.args.dry_run = true;
+
+			self
+		}
+
+		pub fn merge(&mut self, conflict: ConflictPolicy) -> &mut Self {
+			self.args.merge = true;
+			self.args.conflict = conflict;
+
+			self
+		}
+
+		pub fn save(&mut self) -> &mut Self {
+			self.args.save = true;
+
+			self
+		}
+
+		pub fn repo_ref(&mut self, repo_ref: &str) -> &mut Self {
+			self.args.repo_ref = Some(repo_ref.to_string());
+
+			self
+		}
 	}
 }
 
 #[cfg(test)]
 mod tests {
-	use clap::CommandFactory;
+	use clap::{CommandFactory, Parser};
 
 	use super::Cli;
 
@@ -182,4 +415,18 @@ mod tests {
 	fn verify_cli() {
 		Cli::command().debug_assert();
 	}
+
+	#[test]
+	fn test_cli_save_dry_run() {
+		assert!(
+			Cli::try_parse_from([
+				"sca",
+				"create",
+				"foo/bar",
+				"--save",
+				"--dry-run"
+			])
+			.is_err()
+		);
+	}
 }
@@ -1,4 +1,7 @@
-use std::path::{Path, PathBuf};
+use std::{
+	path::{Path, PathBuf},
+	time::Duration,
+};
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
@@ -8,6 +11,21 @@ use crate::json::JsonContent;
 #[derive(Deserialize, Serialize, Default)]
 struct ConfigContent {
 	token: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	retries: Option<u32>,
+	/// In milliseconds
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	retry_delay: Option<u64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	proxy: Option<String>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	ca_bundles: Vec<PathBuf>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	system_certs: Option<bool>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	offline: Option<bool>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	catalogs: Vec<String>,
 }
 
 impl JsonContent for ConfigContent {}
@@ -19,6 +37,8 @@ pub struct Config {
 
 impl Config {
 	pub const FILE_NAME: &'static str = "config.json";
+	const DEFAULT_RETRIES: u32 = 3;
+	const DEFAULT_RETRY_DELAY: u64 = 1000;
 
 	pub fn new(scafalra_dir: &Path) -> Result<Self> {
 		let path = scafalra_dir.join(Self::FILE_NAME);
@@ -30,8 +50,16 @@ impl Config {
 		})
 	}
 
-	pub fn save(&self) -> Result<()> {
-		self.content.save(&self.path)
+	/// The token is the only setting written by scafalra, other settings
+	/// are reloaded so that concurrent edits are kept
+	pub fn save(&mut self) -> Result<()> {
+		let token = self.content.token.clone();
+
+		self.content = ConfigContent::update(&self.path, |content| {
+			content.token = token;
+		})?;
+
+		Ok(())
 	}
 
 	pub fn set_token(&mut self, token: &str) {
@@ -41,6 +69,39 @@ impl Config {
 	pub fn token(&self) -> Option<&str> {
 		self.content.token.as_deref()
 	}
+
+	pub fn retries(&self) -> u32 {
+		self.content.retries.unwrap_or(Self::DEFAULT_RETRIES)
+	}
+
+	pub fn retry_delay(&self) -> Duration {
+		Duration::from_millis(
+			self.content
+				.retry_delay
+				.unwrap_or(Self::DEFAULT_RETRY_DELAY),
+		)
+	}
+
+	pub fn proxy(&self) -> Option<&str> {
+		self.content.proxy.as_deref()
+	}
+
+	pub fn ca_bundles(&self) -> &[PathBuf] {
+		&self.content.ca_bundles
+	}
+
+	pub fn system_certs(&self) -> bool {
+		self.content.system_certs.unwrap_or_default()
+	}
+
+	pub fn offline(&self) -> bool {
+		self.content.offline.unwrap_or_default()
+	}
+
+	/// Urls or paths of the catalog index files
+	pub fn catalogs(&self) -> &[String] {
+		&self.content.catalogs
+	}
 }
 
 #[cfg(test)]
@@ -112,6 +173,7 @@ mod tests {
 		} = ConfigMock::new().with_content();
 
 		assert_eq!(config.token(), Some("token"));
+		assert_eq!(config.retries(), 3);
 	}
 
 	#[test]
@@ -0,0 +1,195 @@
+use std::{
+	env, fs,
+	path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use directories::ProjectDirs;
+use remove_dir_all::remove_dir_all;
+
+use crate::{config::Config, debug, store::Store};
+
+/// Where config.json, store.json and the template cache are placed
+pub struct Dirs {
+	pub config_dir: PathBuf,
+	pub data_dir: PathBuf,
+	pub cache_dir: PathBuf,
+}
+
+impl Dirs {
+	const CACHE_DIR_NAME: &'static str = "cache";
+
+	/// Everything in `dir`, the cache in its `cache` sub directory
+	pub fn single(dir: PathBuf) -> Self {
+		Self {
+			config_dir: dir.clone(),
+			cache_dir: dir.join(Self::CACHE_DIR_NAME),
+			data_dir: dir,
+		}
+	}
+
+	/// The platform directories, `XDG_CONFIG_HOME`, `XDG_DATA_HOME` and
+	/// `XDG_CACHE_HOME` take precedence on every platform
+	pub fn platform() -> Result<Self> {
+		let proj_dirs = project_dirs()?;
+		let xdg_or = |var: &str, default: &Path| {
+			env::var_os(var)
+				.map(PathBuf::from)
+				.filter(|dir| dir.is_absolute())
+				.map(|dir| dir.join("scafalra"))
+				.unwrap_or_else(|| default.to_path_buf())
+		};
+
+		Ok(Self {
+			config_dir: xdg_or("XDG_CONFIG_HOME", proj_dirs.config_dir()),
+			data_dir: xdg_or("XDG_DATA_HOME", proj_dirs.data_dir()),
+			cache_dir: xdg_or("XDG_CACHE_HOME", proj_dirs.cache_dir()),
+		})
+	}
+
+	/// The directory that contained everything before the directories were
+	/// separated
+	pub fn legacy_dir() -> Result<PathBuf> {
+		Ok(project_dirs()?.config_dir().to_path_buf())
+	}
+
+	/// Move config.json, store.json and the cache out of `legacy_dir` if they
+	/// have not been moved yet
+	pub fn migrate(&self, legacy_dir: &Path) -> Result<()> {
+		let legacy_store = legacy_dir.join(Store::FILE_NAME);
+		let legacy_cache = legacy_dir.join(Self::CACHE_DIR_NAME);
+
+		// Older stores contain absolute paths in the legacy cache, loading
+		// the store makes them relative before the cache is moved
+		if legacy_store.exists() && legacy_cache.exists() {
+			if let Err(err) = Store::new(legacy_dir, &legacy_cache) {
+				debug!("failed to load the legacy store: {:?}", err);
+			}
+		}
+
+		let legacy_config = legacy_dir.join(Config::FILE_NAME);
+		let config = self.config_dir.join(Config::FILE_NAME);
+
+		if self.config_dir != legacy_dir
+			&& legacy_config.is_file()
+			&& !config.exists()
+		{
+			move_path(&legacy_config, &config)?;
+		}
+
+		if self.data_dir != legacy_dir {
+			for file_name in [Store::FILE_NAME, Store::BACKUP_FILE_NAME] {
+				let from = legacy_dir.join(file_name);
+				let to = self.data_dir.join(file_name);
+
+				if from.is_file() && !to.exists() {
+					move_path(&from, &to)?;
+				}
+			}
+		}
+
+		if self.cache_dir != legacy_cache
+			&& legacy_cache.is_dir()
+			&& !self.cache_dir.exists()
+		{
+			move_path(&legacy_cache, &self.cache_dir)?;
+		}
+
+		Ok(())
+	}
+
+	pub fn print(&self) -> String {
+		format!(
+			"config: {}\ndata: {}\ncache: {}",
+			self.config_dir.to_string_lossy(),
+			self.data_dir.to_string_lossy(),
+			self.cache_dir.to_string_lossy()
+		)
+	}
+}
+
+fn project_dirs() -> Result<ProjectDirs> {
+	ProjectDirs::from("", "", "scafalra").ok_or(anyhow::anyhow!(
+		"Failed to create scafalra project directory"
+	))
+}
+
+/// Rename `from` to `to`, or copy and remove it if they are on different
+/// file systems
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+	println!(
+		"Moving `{}` to `{}`",
+		from.to_string_lossy(),
+		to.to_string_lossy()
+	);
+
+	if let Some(parent) = to.parent() {
+		fs::create_dir_all(parent)?;
+	}
+
+	if fs::rename(from, to).is_ok() {
+		return Ok(());
+	}
+
+	if from.is_dir() {
+		dircpy::copy_dir(from, to)?;
+		remove_dir_all(from)?;
+	} else {
+		fs::copy(from, to)?;
+		fs::remove_file(from)?;
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use anyhow::Result;
+	use tempfile::tempdir;
+
+	use super::Dirs;
+	use crate::{
+		config::Config,
+		store::{test_utils::StoreJsonMock, Store},
+	};
+
+	#[test]
+	fn test_dirs_migrate() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let legacy_dir = tmp_dir.path().join("legacy");
+		let legacy_bar = legacy_dir.join("cache").join("foo").join("bar");
+		fs::create_dir_all(&legacy_bar)?;
+		fs::write(
+			legacy_dir.join(Store::FILE_NAME),
+			StoreJsonMock::new().push("bar", &legacy_bar).build_v0(),
+		)?;
+		fs::write(
+			legacy_dir.join(Config::FILE_NAME),
+			"{\n  \"token\": \"token\"\n}",
+		)?;
+
+		let dirs = Dirs {
+			config_dir: tmp_dir.path().join("config"),
+			data_dir: tmp_dir.path().join("data"),
+			cache_dir: tmp_dir.path().join("cache"),
+		};
+		dirs.migrate(&legacy_dir)?;
+
+		assert!(!legacy_dir.join(Store::FILE_NAME).exists());
+		assert!(!legacy_dir.join("cache").exists());
+		assert!(!legacy_dir.join(Config::FILE_NAME).exists());
+
+		let config = Config::new(&dirs.config_dir)?;
+		assert_eq!(config.token(), Some("token"));
+
+		let store = Store::new(&dirs.data_dir, &dirs.cache_dir)?;
+		let bar = &store.get("bar").unwrap().path;
+
+		assert_eq!(bar, &dirs.cache_dir.join("foo").join("bar"));
+		assert!(bar.is_dir());
+
+		Ok(())
+	}
+}
@@ -0,0 +1,97 @@
+use std::{fmt, io};
+
+/// Categories of errors, each one is mapped to a distinct exit code
+#[derive(Debug)]
+pub enum ScafalraError {
+	/// Invalid or missing arguments
+	Usage(String),
+	/// A template, file or remote resource does not exist
+	NotFound(String),
+	/// Failed to reach the server
+	Network(String),
+	/// The token is invalid or the rate limit is exceeded
+	Auth(String),
+	/// Failed to read or write files
+	Filesystem(String),
+	/// The destination already exists or files could not be merged
+	Conflict(String),
+	/// Ctrl-C was pressed while files were written
+	Interrupted(String),
+}
+
+impl ScafalraError {
+	pub fn exit_code(&self) -> i32 {
+		match self {
+			Self::Usage(_) => 2,
+			Self::NotFound(_) => 3,
+			Self::Network(_) => 4,
+			Self::Auth(_) => 5,
+			Self::Filesystem(_) => 6,
+			Self::Conflict(_) => 7,
+			Self::Interrupted(_) => 130,
+		}
+	}
+}
+
+impl fmt::Display for ScafalraError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let msg = match self {
+			Self::Usage(msg)
+			| Self::NotFound(msg)
+			| Self::Network(msg)
+			| Self::Auth(msg)
+			| Self::Filesystem(msg)
+			| Self::Conflict(msg)
+			| Self::Interrupted(msg) => msg,
+		};
+
+		write!(f, "{}", msg)
+	}
+}
+
+impl std::error::Error for ScafalraError {}
+
+/// The exit code of an error, errors that are not `ScafalraError` are
+/// categorized by their source, otherwise 1 is used
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+	for cause in err.chain() {
+		if let Some(err) = cause.downcast_ref::<ScafalraError>() {
+			return err.exit_code();
+		}
+
+		if cause.is::<io::Error>() || cause.is::<zip::result::ZipError>() {
+			return ScafalraError::Filesystem(String::new()).exit_code();
+		}
+
+		if cause.is::<ureq::Error>() {
+			return ScafalraError::Network(String::new()).exit_code();
+		}
+	}
+
+	1
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io;
+
+	use anyhow::Context;
+
+	use super::{exit_code, ScafalraError};
+
+	#[test]
+	fn test_exit_code() {
+		let err = anyhow::Error::new(ScafalraError::NotFound("foo".into()));
+		assert_eq!(exit_code(&err), 3);
+
+		let err = anyhow::Error::new(io::Error::other("foo")).context("bar");
+		assert_eq!(exit_code(&err), 6);
+
+		let err = Err::<(), _>(ScafalraError::Conflict("foo".into()))
+			.context("bar")
+			.unwrap_err();
+		assert_eq!(exit_code(&err), 7);
+
+		assert_eq!(exit_code(&anyhow::anyhow!("foo")), 1);
+	}
+}
@@ -1,25 +1,224 @@
+use std::{
+	fmt::Display,
+	io::{self, Write},
+};
+
 use anyhow::Result;
+use crossterm::{
+	cursor,
+	event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+	queue,
+	style::Print,
+	terminal::{self, ClearType},
+};
 use inquire::{MultiSelect, Select, Text};
 
-pub fn select<'a>(
-	options: Vec<&'a String>,
+use crate::{colorize::Colorize, error::ScafalraError};
+
+pub fn select<T: Display>(
+	options: Vec<T>,
 	prompt: &str,
 	msg_when_empty: &str,
-) -> Result<Option<&'a String>> {
+) -> Result<Option<T>> {
 	if options.is_empty() {
-		anyhow::bail!("{}", msg_when_empty);
+		anyhow::bail!(ScafalraError::NotFound(msg_when_empty.to_string()));
 	}
 
 	Ok(Select::new(prompt, options).prompt_skippable()?)
 }
 
-pub fn multi_select<'a>(
+/// Select one of the options, rendering a preview pane of the highlighted
+/// option below the list
+pub fn select_with_preview<'a, F>(
 	options: Vec<&'a String>,
 	prompt: &str,
 	msg_when_empty: &str,
-) -> Result<Option<Vec<&'a String>>> {
+	preview: F,
+) -> Result<Option<&'a String>>
+where
+	F: Fn(&str) -> String,
+{
+	if options.is_empty() {
+		anyhow::bail!(ScafalraError::NotFound(msg_when_empty.to_string()));
+	}
+
+	let mut select = PreviewSelect {
+		options,
+		previews: Vec::new(),
+		cursor: 0,
+		rendered_lines: 0,
+	};
+	select.previews.resize(select.options.len(), None);
+
+	let mut stdout = io::stdout();
+
+	terminal::enable_raw_mode()?;
+	let title = format!("{} {}", "?".green(), prompt);
+	let ret = select.run(&mut stdout, &title, &preview);
+	terminal::disable_raw_mode()?;
+
+	select.clear(&mut stdout)?;
+
+	let ret = ret?;
+
+	match ret {
+		Some(answer) => println!("{} {}", title, answer.blue()),
+		None => println!("{} {}", title, "<canceled>".red()),
+	}
+
+	Ok(ret)
+}
+
+struct PreviewSelect<'a> {
+	options: Vec<&'a String>,
+	previews: Vec<Option<String>>,
+	cursor: usize,
+	rendered_lines: u16,
+}
+
+impl<'a> PreviewSelect<'a> {
+	const PAGE_SIZE: usize = 7;
+	const PREVIEW_HEIGHT: usize = 10;
+
+	fn run<W, F>(
+		&mut self,
+		out: &mut W,
+		title: &str,
+		preview: &F,
+	) -> Result<Option<&'a String>>
+	where
+		W: Write,
+		F: Fn(&str) -> String,
+	{
+		loop {
+			self.render(out, title, preview)?;
+
+			let Event::Key(KeyEvent {
+				code,
+				modifiers,
+				kind,
+				..
+			}) = event::read()?
+			else {
+				continue;
+			};
+
+			if kind == KeyEventKind::Release {
+				continue;
+			}
+
+			match code {
+				KeyCode::Up | KeyCode::Char('k') => {
+					self.cursor = self
+						.cursor
+						.checked_sub(1)
+						.unwrap_or(self.options.len() - 1);
+				}
+				KeyCode::Down | KeyCode::Char('j') => {
+					self.cursor = (self.cursor + 1) % self.options.len();
+				}
+				KeyCode::Enter => return Ok(Some(self.options[self.cursor])),
+				KeyCode::Esc => return Ok(None),
+				KeyCode::Char('c')
+					if modifiers.contains(KeyModifiers::CONTROL) =>
+				{
+					anyhow::bail!("Operation interrupted");
+				}
+				_ => (),
+			}
+		}
+	}
+
+	fn render<W, F>(
+		&mut self,
+		out: &mut W,
+		title: &str,
+		preview: &F,
+	) -> Result<()>
+	where
+		W: Write,
+		F: Fn(&str) -> String,
+	{
+		let width = terminal::size().map(|(w, _)| w as usize).unwrap_or(80);
+		let mut lines = vec![title.to_string()];
+
+		let page_start = self
+			.cursor
+			.saturating_sub(Self::PAGE_SIZE - 1)
+			.min(self.options.len().saturating_sub(Self::PAGE_SIZE));
+
+		for (idx, option) in self
+			.options
+			.iter()
+			.enumerate()
+			.skip(page_start)
+			.take(Self::PAGE_SIZE)
+		{
+			let line = truncate(option, width.saturating_sub(2));
+			if idx == self.cursor {
+				lines.push(format!("{} {}", ">".blue(), line.blue()));
+			} else {
+				lines.push(format!("  {}", line));
+			}
+		}
+
+		lines.push("─".repeat(width.min(40)));
+
+		let preview_content = self.previews[self.cursor]
+			.get_or_insert_with(|| preview(self.options[self.cursor]));
+
+		lines.extend(
+			preview_content
+				.lines()
+				.take(Self::PREVIEW_HEIGHT)
+				.map(|line| truncate(line, width)),
+		);
+
+		self.clear(out)?;
+
+		for (idx, line) in lines.iter().enumerate() {
+			if idx > 0 {
+				queue!(out, Print("\r\n"))?;
+			}
+			queue!(out, Print(line))?;
+		}
+
+		out.flush()?;
+
+		self.rendered_lines = lines.len() as u16;
+
+		Ok(())
+	}
+
+	fn clear<W: Write>(&mut self, out: &mut W) -> Result<()> {
+		if self.rendered_lines > 1 {
+			queue!(out, cursor::MoveToPreviousLine(self.rendered_lines - 1))?;
+		}
+
+		queue!(
+			out,
+			cursor::MoveToColumn(0),
+			terminal::Clear(ClearType::FromCursorDown)
+		)?;
+		out.flush()?;
+
+		self.rendered_lines = 0;
+
+		Ok(())
+	}
+}
+
+fn truncate(s: &str, width: usize) -> String {
+	s.chars().take(width).collect()
+}
+
+pub fn multi_select<T: Display>(
+	options: Vec<T>,
+	prompt: &str,
+	msg_when_empty: &str,
+) -> Result<Option<Vec<T>>> {
 	if options.is_empty() {
-		anyhow::bail!("{}", msg_when_empty);
+		anyhow::bail!(ScafalraError::NotFound(msg_when_empty.to_string()));
 	}
 
 	Ok(MultiSelect::new(prompt, options).prompt_skippable()?)
@@ -1,6 +1,13 @@
-use std::{fs, path::Path};
+use std::{
+	fs::{self, File, OpenOptions},
+	io::{self, Write},
+	path::{Path, PathBuf},
+	process,
+	sync::atomic::{AtomicUsize, Ordering},
+};
 
 use anyhow::Result;
+use fs4::FileExt;
 use serde::{de::DeserializeOwned, Serialize};
 
 pub trait JsonContent
@@ -14,20 +21,108 @@ where
 				let value: Self = serde_json::from_str(&content)?;
 				return Ok(value);
 			}
+
+			return Ok(Self::default());
 		}
 
 		let default = Self::default();
-		fs::write(file_path, serde_json::to_string_pretty(&default)?)?;
+
+		// Another process may have created the file in the meantime, which
+		// must not be overwritten
+		match OpenOptions::new()
+			.write(true)
+			.create_new(true)
+			.open(file_path)
+		{
+			Ok(mut file) => {
+				file.write_all(
+					serde_json::to_string_pretty(&default)?.as_bytes(),
+				)?
+			}
+			Err(err) if err.kind() == io::ErrorKind::AlreadyExists => (),
+			Err(err) => return Err(err.into()),
+		}
 
 		Ok(default)
 	}
 
+	/// Write to a temporary file and rename it, so that the file is never
+	/// left partially written
 	fn save(&self, file_path: &Path) -> Result<()> {
+		static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
 		let content = serde_json::to_string_pretty(&self)?;
-		fs::write(file_path, content)?;
+		let tmp_path = sibling_path(
+			file_path,
+			&format!(
+				".{}.{}.tmp",
+				process::id(),
+				COUNTER.fetch_add(1, Ordering::Relaxed)
+			),
+		);
+
+		let mut file = File::create(&tmp_path)?;
+		file.write_all(content.as_bytes())?;
+		file.sync_all()?;
+		drop(file);
+
+		if let Err(err) = fs::rename(&tmp_path, file_path) {
+			let _ = fs::remove_file(&tmp_path);
+			return Err(err.into());
+		}
 
 		Ok(())
 	}
+
+	/// Load the latest content while holding the lock of the file, apply `f`
+	/// to it and save it, so that concurrent updates are not lost
+	fn update<F>(file_path: &Path, f: F) -> Result<Self>
+	where
+		F: FnOnce(&mut Self),
+	{
+		let _lock = FileLock::acquire(file_path)?;
+
+		let mut value = Self::load(file_path)?;
+		f(&mut value);
+		value.save(file_path)?;
+
+		Ok(value)
+	}
+}
+
+/// An advisory lock on `<file>.lock`, released when dropped
+pub struct FileLock {
+	file: File,
+}
+
+impl FileLock {
+	pub fn acquire(file_path: &Path) -> Result<Self> {
+		let file = OpenOptions::new()
+			.create(true)
+			.truncate(false)
+			.write(true)
+			.open(sibling_path(file_path, ".lock"))?;
+
+		file.lock_exclusive()?;
+
+		Ok(Self {
+			file,
+		})
+	}
+}
+
+impl Drop for FileLock {
+	fn drop(&mut self) {
+		let _ = self.file.unlock();
+	}
+}
+
+/// `file_path` with `suffix` appended to the file name
+fn sibling_path(file_path: &Path, suffix: &str) -> PathBuf {
+	let mut file_name = file_path.file_name().unwrap_or_default().to_owned();
+	file_name.push(suffix);
+
+	file_path.with_file_name(file_name)
 }
 
 #[cfg(test)]
@@ -95,7 +190,10 @@ mod tests {
 
 	use anyhow::Result;
 
-	use super::{test_utils::JsonContentMock, JsonContent};
+	use super::{
+		test_utils::{Foo, JsonContentMock},
+		JsonContent,
+	};
 
 	#[test]
 	fn test_json_load_file() {
@@ -147,4 +245,26 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[test]
+	fn test_json_update() -> Result<()> {
+		let JsonContentMock {
+			tmp_dir,
+			foo,
+			path,
+		} = JsonContentMock::new().with_content();
+
+		let updated = Foo::update(&path, |foo| foo.bar.push('2'))?;
+
+		assert_eq!(foo.bar, "bar");
+		assert_eq!(updated.bar, "bar2");
+		assert_eq!(fs::read_to_string(&path)?, "{\n  \"bar\": \"bar2\"\n}");
+		assert_eq!(
+			fs::read_dir(tmp_dir.path())?.count(),
+			2,
+			"only the file and its lock file are left"
+		);
+
+		Ok(())
+	}
 }
@@ -1,29 +1,46 @@
+mod answers;
 mod api;
+mod catalog;
 mod cli;
 mod colorize;
 mod config;
 mod debug;
+mod dirs;
+mod error;
 mod interactive;
 mod json;
+mod manifest;
 mod path_ext;
+mod plan;
+mod progress;
 mod repository;
 mod scafalra;
+mod source;
+mod staging;
 mod store;
 mod sub_template;
 mod template;
+mod tls;
+mod tree;
+mod upgrade;
 
-use std::env;
+use std::{env, process};
 
 use anyhow::Result;
 use clap::Parser;
 use cli::{Cli, Command};
-use debug::trun_on_debug;
-use directories::ProjectDirs;
+use debug::{is_debug_mode, trun_on_debug};
+use dirs::Dirs;
 use scafalra::Scafalra;
 
 fn main() {
 	if let Err(err) = run() {
-		eprintln!("{}", err);
+		if is_debug_mode() {
+			eprintln!("{:?}", err);
+		} else {
+			eprintln!("{}", err);
+		}
+		process::exit(error::exit_code(&err));
 	}
 }
 
@@ -34,36 +51,52 @@ fn run() -> Result<()> {
 		trun_on_debug();
 	}
 
-	let scfalra_dir = if cfg!(feature = "_dev") {
-		std::path::PathBuf::from("tmp/sca-test")
+	if cli.quiet {
+		progress::turn_on_quiet();
+	}
+
+	let dirs = if let Some(home) = cli.home {
+		Dirs::single(home)
+	} else if cfg!(feature = "_dev") {
+		Dirs::single(std::path::PathBuf::from("tmp/sca-test"))
 	} else {
-		ProjectDirs::from("", "", "scafalra")
-			.ok_or(anyhow::anyhow!(
-				"Failed to create scafalra project directory"
-			))?
-			.config_dir()
-			.to_path_buf()
+		let dirs = Dirs::platform()?;
+		dirs.migrate(&Dirs::legacy_dir()?)?;
+		dirs
 	};
 
-	let mut scafalra = Scafalra::new(scfalra_dir, None, cli.token.as_deref())?;
-
 	if cli.proj_dir {
-		println!("{}", scafalra.path.to_string_lossy());
+		println!("{}", dirs.print());
 		return Ok(());
 	}
 
+	let mut scafalra = Scafalra::new(dirs, None, cli.token.as_deref())?;
+
 	if cli.interactive {
 		scafalra.interactive_mode = true;
 	}
 
+	if cli.offline {
+		scafalra.set_offline();
+	}
+
 	if let Some(command) = cli.command {
 		match command {
-			Command::List(args) => scafalra.list(args),
+			Command::List(args) => scafalra.list(args)?,
 			Command::Remove(args) => scafalra.remove(args)?,
 			Command::Rename(args) => scafalra.rename(args)?,
 			Command::Add(args) => scafalra.add(args)?,
 			Command::Create(args) => scafalra.create(args)?,
 			Command::Token(args) => scafalra.token(args)?,
+			Command::Show(args) => scafalra.show(args)?,
+			Command::Upgrade(args) => scafalra.upgrade(args)?,
+			Command::RateLimit => scafalra.rate_limit()?,
+			Command::Doctor(args) => scafalra.doctor(args)?,
+			Command::Export => scafalra.export()?,
+			Command::Import(args) => scafalra.import(args)?,
+			Command::Sync(args) => scafalra.sync(args)?,
+			Command::Search(args) => scafalra.search(args)?,
+			Command::Catalog => scafalra.catalog()?,
 		}
 	}
 
@@ -0,0 +1,194 @@
+use std::{
+	collections::BTreeMap,
+	fs,
+	path::{Component, Path, PathBuf},
+};
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::{error::ScafalraError, sub_template::SUB_TEMPLATE_DIR};
+
+pub const MANIFEST_FILE: &str = "manifest.toml";
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Manifest {
+	pub description: Option<String>,
+	/// Names of the templates that are created before this one, in order
+	pub extends: Vec<String>,
+	pub sub_templates: BTreeMap<String, SubTemplateMeta>,
+}
+
+/// The `[sub_templates.<name>]` tables of the manifest
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct SubTemplateMeta {
+	pub description: Option<String>,
+	/// Where it is copied, relative to the destination, defaults to its name
+	pub target: Option<PathBuf>,
+	/// Copy the contents into the root of the destination
+	pub merge: bool,
+	/// Sub templates that are created together with it
+	pub requires: Vec<String>,
+	/// Sub templates that can not be created together with it
+	pub conflicts: Vec<String>,
+}
+
+impl SubTemplateMeta {
+	/// Where the sub template `name` is copied, relative to the destination
+	pub fn target(&self, name: &str) -> Result<PathBuf> {
+		if self.merge {
+			return Ok(PathBuf::new());
+		}
+
+		let Some(target) = &self.target else {
+			return Ok(PathBuf::from(name));
+		};
+
+		if !target.components().all(|comp| {
+			matches!(comp, Component::Normal(_) | Component::CurDir)
+		}) {
+			anyhow::bail!(ScafalraError::Usage(format!(
+				"The target `{}` of sub template `{}` must be a relative path inside the destination",
+				target.to_string_lossy(),
+				name
+			)));
+		}
+
+		Ok(target.clone())
+	}
+}
+
+impl Manifest {
+	pub fn sub_template(&self, name: &str) -> SubTemplateMeta {
+		self.sub_templates.get(name).cloned().unwrap_or_default()
+	}
+
+	pub fn load(template_path: &Path) -> Result<Self> {
+		let file_path =
+			template_path.join(SUB_TEMPLATE_DIR).join(MANIFEST_FILE);
+
+		if !file_path.is_file() {
+			return Ok(Self::default());
+		}
+
+		let content = fs::read_to_string(&file_path)?;
+		let manifest = toml::from_str(&content).map_err(|err| {
+			anyhow::anyhow!(
+				"Failed to parse `{}`: {}",
+				file_path.to_string_lossy(),
+				err.message()
+			)
+		})?;
+
+		Ok(manifest)
+	}
+}
+
+#[cfg(test)]
+pub mod test_utils {
+	use std::{fs, path::Path};
+
+	use super::MANIFEST_FILE;
+	use crate::sub_template::SUB_TEMPLATE_DIR;
+
+	pub fn manifest_setup(template_path: &Path, content: &str) {
+		let sub_template_path = template_path.join(SUB_TEMPLATE_DIR);
+		fs::create_dir_all(&sub_template_path).unwrap();
+		fs::write(sub_template_path.join(MANIFEST_FILE), content).unwrap();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::path::Path;
+
+	use anyhow::Result;
+	use tempfile::tempdir;
+
+	use super::{test_utils::manifest_setup, Manifest};
+
+	#[test]
+	fn test_manifest_load_not_exists() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let manifest = Manifest::load(tmp_dir.path())?;
+
+		assert_eq!(manifest.description, None);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_manifest_load() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		manifest_setup(tmp_dir.path(), "description = \"foo\"");
+
+		let manifest = Manifest::load(tmp_dir.path())?;
+
+		assert_eq!(manifest.description.as_deref(), Some("foo"));
+		assert!(manifest.extends.is_empty());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_manifest_load_extends() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		manifest_setup(tmp_dir.path(), "extends = [\"base\", \"ci\"]");
+
+		let manifest = Manifest::load(tmp_dir.path())?;
+
+		assert_eq!(manifest.extends, vec!["base", "ci"]);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_manifest_load_sub_templates() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		manifest_setup(
+			tmp_dir.path(),
+			r#"
+[sub_templates.ci]
+description = "GitHub Actions"
+target = ".github/workflows"
+requires = ["lint"]
+
+[sub_templates.docs]
+merge = true
+
+[sub_templates.bad]
+target = "../bad"
+"#,
+		);
+
+		let manifest = Manifest::load(tmp_dir.path())?;
+		let ci = manifest.sub_template("ci");
+
+		assert_eq!(ci.description.as_deref(), Some("GitHub Actions"));
+		assert_eq!(ci.requires, vec!["lint"]);
+		assert_eq!(ci.target("ci")?, Path::new(".github/workflows"));
+		assert_eq!(
+			manifest.sub_template("docs").target("docs")?,
+			Path::new("")
+		);
+		assert_eq!(
+			manifest.sub_template("foo").target("foo")?,
+			Path::new("foo")
+		);
+		assert!(manifest.sub_template("bad").target("bad").is_err());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_manifest_load_bad_content() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		manifest_setup(tmp_dir.path(), "description =");
+
+		assert!(Manifest::load(tmp_dir.path()).is_err());
+
+		Ok(())
+	}
+}
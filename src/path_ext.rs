@@ -30,6 +30,29 @@ impl JoinCanonicalize for PathBuf {
 	}
 }
 
+/// The components of a path that may have been written on another platform,
+/// both `/` and `\` are separators
+pub fn portable_components(path: &Path) -> Vec<String> {
+	path.to_string_lossy()
+		.split(['/', '\\'])
+		.filter(|comp| !comp.is_empty())
+		.map(String::from)
+		.collect()
+}
+
+/// Like `Path::is_absolute`, but also true for `/foo` and `C:\foo` on every
+/// platform
+pub fn is_portable_absolute(path: &Path) -> bool {
+	let s = path.to_string_lossy();
+	let bytes = s.as_bytes();
+
+	path.is_absolute()
+		|| s.starts_with(['/', '\\'])
+		|| (bytes.len() >= 2
+			&& bytes[0].is_ascii_alphabetic()
+			&& bytes[1] == b':')
+}
+
 #[cfg(test)]
 pub use join_slash_ext::JoinSlash;
 
@@ -0,0 +1,472 @@
+use std::{
+	collections::BTreeMap,
+	fs,
+	path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+use crate::{
+	error::ScafalraError, staging::check_interrupted,
+	sub_template::SUB_TEMPLATE_DIR,
+};
+
+pub struct PlanEntry {
+	pub source: PathBuf,
+	pub size: u64,
+	pub is_dir: bool,
+}
+
+/// The files that `create` is going to write, keyed by the path relative to
+/// the destination
+#[derive(Default)]
+pub struct Plan {
+	entries: BTreeMap<PathBuf, PlanEntry>,
+	overridden: Vec<PathBuf>,
+}
+
+impl Plan {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add all contents of `src_dir` to `target`(relative to the destination),
+	/// the `.scafalra` directory in the root of `src_dir` is skipped
+	pub fn add_dir(&mut self, src_dir: &Path, target: &Path) -> Result<()> {
+		if !target.as_os_str().is_empty() {
+			self.insert(target.to_path_buf(), src_dir, 0, true);
+		}
+
+		self.walk(src_dir, target, true)
+	}
+
+	fn walk(&mut self, dir: &Path, target: &Path, is_root: bool) -> Result<()> {
+		for entry in fs::read_dir(dir)? {
+			let entry = entry?;
+			let file_name = entry.file_name();
+
+			if is_root && file_name == SUB_TEMPLATE_DIR {
+				continue;
+			}
+
+			let source = entry.path();
+			let path = target.join(&file_name);
+			let metadata = fs::metadata(&source)?;
+
+			if metadata.is_dir() {
+				self.insert(path.clone(), &source, 0, true);
+				self.walk(&source, &path, false)?;
+			} else {
+				self.insert(path, &source, metadata.len(), false);
+			}
+		}
+
+		Ok(())
+	}
+
+	fn insert(
+		&mut self,
+		path: PathBuf,
+		source: &Path,
+		size: u64,
+		is_dir: bool,
+	) {
+		let prev = self.entries.insert(
+			path.clone(),
+			PlanEntry {
+				source: source.to_path_buf(),
+				size,
+				is_dir,
+			},
+		);
+
+		if let Some(prev) = prev {
+			if !(prev.is_dir && is_dir) {
+				self.overridden.push(path);
+			}
+		}
+	}
+
+	pub fn entries(&self) -> impl Iterator<Item = (&PathBuf, &PlanEntry)> {
+		self.entries.iter()
+	}
+
+	/// Paths that were provided by more than one source, the last one wins
+	pub fn overridden(&self) -> &[PathBuf] {
+		&self.overridden
+	}
+
+	/// Files of the plan that already exist in `dest`
+	pub fn existing(&self, dest: &Path) -> Vec<&PathBuf> {
+		self.entries
+			.iter()
+			.filter(|(path, entry)| !entry.is_dir && dest.join(path).exists())
+			.map(|(path, _)| path)
+			.collect()
+	}
+
+	pub fn total_size(&self) -> u64 {
+		self.entries.values().map(|entry| entry.size).sum()
+	}
+
+	pub fn execute(&self, dest: &Path) -> Result<()> {
+		fs::create_dir_all(dest)?;
+
+		for (path, entry) in &self.entries {
+			check_interrupted()?;
+
+			let target = dest.join(path);
+
+			if entry.is_dir {
+				fs::create_dir_all(&target)?;
+			} else {
+				if let Some(parent) = target.parent() {
+					fs::create_dir_all(parent)?;
+				}
+				fs::copy(&entry.source, &target)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Like `execute`, but `dest` may already exist, `resolve` decides what to
+	/// do with each file that already exists and has different contents. The
+	/// files are written to `out`, which is `dest` or a staging directory
+	pub fn merge<F>(
+		&self,
+		dest: &Path,
+		out: &Path,
+		mut resolve: F,
+	) -> Result<MergeReport>
+	where
+		F: FnMut(&Path) -> Result<Resolution>,
+	{
+		let mut report = MergeReport::default();
+
+		fs::create_dir_all(out)?;
+
+		for (path, entry) in &self.entries {
+			check_interrupted()?;
+
+			let target = dest.join(path);
+
+			if entry.is_dir {
+				if target.is_file() {
+					anyhow::bail!(ScafalraError::Conflict(format!(
+						"`{}` is a file in the destination but a directory in the template",
+						path.to_string_lossy()
+					)));
+				}
+				fs::create_dir_all(out.join(path))?;
+				continue;
+			}
+
+			let out_target = out.join(path);
+
+			if let Some(parent) = out_target.parent() {
+				fs::create_dir_all(parent)?;
+			}
+
+			if !target.exists() {
+				fs::copy(&entry.source, &out_target)?;
+				report.written.push(path.clone());
+				continue;
+			}
+
+			if target.is_dir() {
+				anyhow::bail!(ScafalraError::Conflict(format!(
+					"`{}` is a directory in the destination but a file in the template",
+					path.to_string_lossy()
+				)));
+			}
+
+			if fs::read(&target)? == fs::read(&entry.source)? {
+				report.unchanged.push(path.clone());
+				continue;
+			}
+
+			match resolve(path)? {
+				Resolution::Skip => report.skipped.push(path.clone()),
+				Resolution::Overwrite => {
+					fs::copy(&entry.source, &out_target)?;
+					report.overwritten.push(path.clone());
+				}
+				Resolution::KeepBoth => {
+					let kept_path = keep_both_path(dest, path);
+					fs::copy(&entry.source, out.join(&kept_path))?;
+					report.written.push(kept_path);
+				}
+			}
+		}
+
+		Ok(report)
+	}
+
+	pub fn print(&self) -> String {
+		let mut lines = Vec::new();
+		let mut files = 0;
+		let mut dirs = 0;
+
+		for (path, entry) in &self.entries {
+			let path = path.to_string_lossy();
+
+			if entry.is_dir {
+				dirs += 1;
+				lines.push(format!("{:>10}  {}/", "-", path));
+			} else {
+				files += 1;
+				lines.push(format!(
+					"{:>10}  {}",
+					format_size(entry.size),
+					path
+				));
+			}
+		}
+
+		lines.push(format!(
+			"{} files, {} directories, {} in total",
+			files,
+			dirs,
+			format_size(self.total_size())
+		));
+
+		lines.join("\n")
+	}
+
+	pub fn print_conflicts(&self, dest: &Path) -> Option<String> {
+		use crate::colorize::Colorize;
+
+		let mut lines = Vec::new();
+
+		for path in &self.overridden {
+			lines.push(format!(
+				"{} `{}` is provided by multiple sources, the last one is used",
+				"!".red(),
+				path.to_string_lossy()
+			));
+		}
+
+		for path in self.existing(dest) {
+			lines.push(format!(
+				"{} `{}` already exists in the destination",
+				"!".red(),
+				path.to_string_lossy()
+			));
+		}
+
+		if lines.is_empty() {
+			return None;
+		}
+
+		Some(lines.join("\n"))
+	}
+}
+
+#[derive(Clone, Copy)]
+pub enum Resolution {
+	Skip,
+	Overwrite,
+	KeepBoth,
+}
+
+impl std::fmt::Display for Resolution {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let s = match self {
+			Self::Skip => "Skip",
+			Self::Overwrite => "Overwrite",
+			Self::KeepBoth => "Keep both",
+		};
+
+		write!(f, "{}", s)
+	}
+}
+
+#[derive(Default)]
+pub struct MergeReport {
+	pub written: Vec<PathBuf>,
+	pub overwritten: Vec<PathBuf>,
+	pub skipped: Vec<PathBuf>,
+	pub unchanged: Vec<PathBuf>,
+}
+
+impl MergeReport {
+	pub fn print(&self) -> String {
+		use crate::colorize::Colorize;
+
+		let mut lines = Vec::new();
+
+		for path in &self.written {
+			lines.push(format!("{} {}", "+".green(), path.to_string_lossy()));
+		}
+
+		for path in &self.overwritten {
+			lines.push(format!("{} {}", "~".blue(), path.to_string_lossy()));
+		}
+
+		for path in &self.skipped {
+			lines.push(format!("{} {}", "=".red(), path.to_string_lossy()));
+		}
+
+		lines.push(format!(
+			"{} written, {} overwritten, {} skipped, {} unchanged",
+			self.written.len(),
+			self.overwritten.len(),
+			self.skipped.len(),
+			self.unchanged.len()
+		));
+
+		lines.join("\n")
+	}
+}
+
+/// `dir/foo.txt` -> `dir/foo-scafalra.txt`, a number is appended if that is
+/// taken too
+fn keep_both_path(dest: &Path, path: &Path) -> PathBuf {
+	let stem = path
+		.file_stem()
+		.map(|stem| stem.to_string_lossy().to_string())
+		.unwrap_or_default();
+	let ext = path
+		.extension()
+		.map(|ext| format!(".{}", ext.to_string_lossy()))
+		.unwrap_or_default();
+
+	let mut n = 0;
+
+	loop {
+		let suffix = if n == 0 {
+			"-scafalra".to_string()
+		} else {
+			format!("-scafalra-{}", n)
+		};
+
+		let candidate =
+			path.with_file_name(format!("{}{}{}", stem, suffix, ext));
+
+		if !dest.join(&candidate).exists() {
+			return candidate;
+		}
+
+		n += 1;
+	}
+}
+
+pub fn format_size(size: u64) -> String {
+	const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+	if size < 1024 {
+		return format!("{} B", size);
+	}
+
+	let mut value = size as f64;
+	let mut unit = "B";
+
+	for next_unit in UNITS {
+		if value < 1024.0 {
+			break;
+		}
+		value /= 1024.0;
+		unit = next_unit;
+	}
+
+	format!("{:.1} {}", value, unit)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{fs, path::Path};
+
+	use anyhow::Result;
+	use similar_asserts::assert_eq;
+	use tempfile::tempdir;
+	use test_case::test_case;
+
+	use super::{format_size, Plan, Resolution};
+	use crate::{
+		path_ext::*, sub_template::test_utils::sub_tempaltes_dir_setup,
+	};
+
+	#[test]
+	fn test_plan_add_dir() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let tpl_dir = tmp_dir.path().join("tpl");
+		fs::create_dir_all(tpl_dir.join("a"))?;
+		fs::write(tpl_dir.join_slash("a/foo.txt"), "foo")?;
+		fs::write(tpl_dir.join("bar.txt"), "bar")?;
+		sub_tempaltes_dir_setup(&tpl_dir, &["dir-1"]);
+		fs::write(tpl_dir.join_slash(".scafalra/dir-1/bar.txt"), "bar2")?;
+
+		let mut plan = Plan::new();
+		plan.add_dir(&tpl_dir, Path::new(""))?;
+		plan.add_dir(&tpl_dir.join_slash(".scafalra/dir-1"), Path::new(""))?;
+
+		let paths = plan
+			.entries()
+			.map(|(path, _)| path.to_string_lossy().replace('\\', "/"))
+			.collect::<Vec<_>>();
+
+		assert_eq!(paths, vec!["a", "a/foo.txt", "bar.txt"]);
+		assert_eq!(plan.overridden(), [Path::new("bar.txt")]);
+		assert_eq!(plan.total_size(), 7);
+
+		let dest = tmp_dir.path().join("dest");
+		plan.execute(&dest)?;
+
+		assert_eq!(fs::read_to_string(dest.join("bar.txt"))?, "bar2");
+		assert!(dest.join_slash("a/foo.txt").exists());
+		assert_eq!(plan.existing(&dest).len(), 2);
+
+		Ok(())
+	}
+
+	#[test_case(Resolution::Skip, "old", None; "skip")]
+	#[test_case(Resolution::Overwrite, "new", None; "overwrite")]
+	#[test_case(Resolution::KeepBoth, "old", Some("new"); "keep both")]
+	fn test_plan_merge(
+		resolution: Resolution,
+		expect: &str,
+		expect_kept: Option<&str>,
+	) -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let tpl_dir = tmp_dir.path().join("tpl");
+		let dest = tmp_dir.path().join("dest");
+		fs::create_dir_all(&tpl_dir)?;
+		fs::create_dir_all(&dest)?;
+		fs::write(tpl_dir.join("foo.txt"), "new")?;
+		fs::write(tpl_dir.join("bar.txt"), "bar")?;
+		fs::write(tpl_dir.join("baz.txt"), "baz")?;
+		fs::write(dest.join("foo.txt"), "old")?;
+		fs::write(dest.join("baz.txt"), "baz")?;
+
+		let mut plan = Plan::new();
+		plan.add_dir(&tpl_dir, Path::new(""))?;
+
+		let mut resolved = Vec::new();
+		let report = plan.merge(&dest, &dest, |path| {
+			resolved.push(path.to_path_buf());
+			Ok(resolution)
+		})?;
+
+		assert_eq!(resolved, [Path::new("foo.txt")]);
+		assert_eq!(fs::read_to_string(dest.join("foo.txt"))?, expect);
+		assert_eq!(fs::read_to_string(dest.join("bar.txt"))?, "bar");
+		assert_eq!(
+			fs::read_to_string(dest.join("foo-scafalra.txt"))
+				.ok()
+				.as_deref(),
+			expect_kept
+		);
+		assert_eq!(report.unchanged, [Path::new("baz.txt")]);
+
+		Ok(())
+	}
+
+	#[test_case(0, "0 B")]
+	#[test_case(1023, "1023 B")]
+	#[test_case(1024, "1.0 KiB")]
+	#[test_case(1536 * 1024, "1.5 MiB")]
+	fn test_format_size(size: u64, expect: &str) {
+		assert_eq!(format_size(size), expect);
+	}
+}
@@ -0,0 +1,57 @@
+use std::{
+	io::{self, IsTerminal},
+	sync::atomic::{AtomicBool, Ordering},
+};
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn turn_on_quiet() {
+	QUIET.store(true, Ordering::Relaxed);
+}
+
+fn is_enabled() -> bool {
+	!QUIET.load(Ordering::Relaxed) && io::stdout().is_terminal()
+}
+
+/// A progress bar of downloaded bytes, a spinner is used when `total` is
+/// unknown
+pub fn download_bar(total: Option<u64>) -> ProgressBar {
+	if !is_enabled() {
+		return ProgressBar::hidden();
+	}
+
+	match total {
+		Some(total) => {
+			ProgressBar::new(total).with_style(
+				ProgressStyle::with_template(
+					"[{bar:30}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+				)
+				.unwrap()
+				.progress_chars("=> "),
+			)
+		}
+		None => {
+			ProgressBar::new_spinner().with_style(
+				ProgressStyle::with_template(
+					"{spinner} {bytes} ({bytes_per_sec})",
+				)
+				.unwrap(),
+			)
+		}
+	}
+}
+
+/// A progress bar of extracted files
+pub fn extract_bar(total: u64) -> ProgressBar {
+	if !is_enabled() {
+		return ProgressBar::hidden();
+	}
+
+	ProgressBar::new(total).with_style(
+		ProgressStyle::with_template("[{bar:30}] {pos}/{len} files")
+			.unwrap()
+			.progress_chars("=> "),
+	)
+}
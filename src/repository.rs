@@ -3,6 +3,8 @@ use std::sync::OnceLock;
 use anyhow::Result;
 use regex::Regex;
 
+use crate::error::ScafalraError;
+
 fn repo_re() -> &'static Regex {
 	static REPO_RE: OnceLock<Regex> = OnceLock::new();
 
@@ -20,9 +22,13 @@ pub struct Repository {
 
 impl Repository {
 	pub fn parse(input: &str) -> Result<Self> {
-		let caps = repo_re()
-			.captures(input)
-			.ok_or(anyhow::anyhow!("Could not parse the input: `{}`", input))?;
+		let caps =
+			repo_re()
+				.captures(input)
+				.ok_or(ScafalraError::Usage(format!(
+					"Could not parse the input: `{}`",
+					input
+				)))?;
 
 		let owner = caps[1].to_string();
 		let mut name = caps[2].to_string();
@@ -37,6 +43,26 @@ impl Repository {
 		})
 	}
 
+	/// Parse `owner/name/path/to/dir`, the path after the repository is
+	/// returned as the subdir
+	pub fn parse_with_subdir(input: &str) -> Result<(Self, Option<String>)> {
+		let path = input.strip_prefix("https://github.com/").unwrap_or(input);
+		let mut parts = path.splitn(3, '/');
+		let repo = match (parts.next(), parts.next()) {
+			(Some(owner), Some(name)) => {
+				Self::parse(&format!("{}/{}", owner, name))?
+			}
+			_ => Self::parse(input)?,
+		};
+		let subdir = parts
+			.next()
+			.map(|subdir| subdir.trim_matches('/'))
+			.filter(|subdir| !subdir.is_empty())
+			.map(String::from);
+
+		Ok((repo, subdir))
+	}
+
 	pub fn url(&self) -> String {
 		if cfg!(test) {
 			"url".to_string()
@@ -66,6 +92,22 @@ mod tests {
 		Ok(())
 	}
 
+	#[test_case("foo/bar", None; "no subdir")]
+	#[test_case("foo/bar/a/a1", Some("a/a1"); "subdir")]
+	#[test_case("https://github.com/foo/bar/a/", Some("a"); "url")]
+	fn test_repo_parse_with_subdir(
+		input: &str,
+		subdir: Option<&str>,
+	) -> Result<()> {
+		let (repo, actual) = Repository::parse_with_subdir(input)?;
+
+		assert_eq!(repo.owner, "foo");
+		assert_eq!(repo.name, "bar");
+		assert_eq!(actual.as_deref(), subdir);
+
+		Ok(())
+	}
+
 	#[test_case(""; "empty")]
 	#[test_case("foo"; "incomplete")]
 	#[test_case("foo/bar/baz"; "paths exceeded")]
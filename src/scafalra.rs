@@ -1,53 +1,88 @@
 use std::{
-	collections::HashMap,
-	env, fs,
-	path::{Path, PathBuf},
+	cell::OnceCell,
+	collections::BTreeMap,
+	env, fs, io,
+	path::{Component, Path, PathBuf},
 };
 
 use anyhow::Result;
+use indicatif::ProgressBar;
 use remove_dir_all::remove_dir_all;
 
 use crate::{
+	answers::Answers,
 	api::GitHubApi,
-	cli::{AddArgs, CreateArgs, ListArgs, RemoveArgs, RenameArgs, TokenArgs},
+	catalog::{print_entries, Catalog},
+	cli::{
+		AddArgs, ConflictPolicy, CreateArgs, DoctorArgs, ImportArgs, ListArgs,
+		RemoveArgs, RenameArgs, SearchArgs, ShowArgs, SyncArgs, TokenArgs,
+		UpgradeArgs,
+	},
+	colorize::Colorize,
 	config::Config,
 	debug,
-	interactive::{input, multi_select, select},
+	dirs::Dirs,
+	error::ScafalraError,
+	interactive::{input, multi_select, select, select_with_preview},
+	manifest::{Manifest, SubTemplateMeta},
 	path_ext::*,
+	plan::{Plan, Resolution},
+	progress,
 	repository::Repository,
+	source::{read_file, Source, SourceList},
+	staging::Staging,
 	store::Store,
-	sub_template::SUB_TEMPLATE_DIR,
+	sub_template::{
+		read_sub_template_metas, read_sub_templates, resolve_selection,
+		SubTemplate,
+	},
 	template::Template,
+	tree::render_tree,
+	upgrade::upgrade,
 };
 
 pub struct Scafalra {
 	pub path: PathBuf,
 	cache_dir: PathBuf,
 	config: Config,
-	store: Store,
+	store: OnceCell<Store>,
 	github_api: GitHubApi,
 	pub interactive_mode: bool,
 }
 
 impl Scafalra {
-	const CACHE_DIR_NAME: &'static str = "cache";
 	const TMP_DIR_NAME: &'static str = "t";
+	const UPGRADE_DIR_NAME: &'static str = "@upgrade";
+	const LOCAL_DIR_NAME: &'static str = "@local";
+	const CATALOG_DIR_NAME: &'static str = "@catalog";
+	/// Not a valid GitHub owner, so it can't collide with cached templates
+	const REMOTE_DIR_NAME: &'static str = "@remote";
 
 	pub fn new(
-		path: PathBuf,
+		dirs: Dirs,
 		endpoint: Option<&str>,
 		token: Option<&str>,
 	) -> Result<Self> {
-		let cache_dir = path.join(Self::CACHE_DIR_NAME);
+		let Dirs {
+			config_dir,
+			data_dir: path,
+			cache_dir,
+		} = dirs;
 
-		if !cache_dir.exists() {
-			fs::create_dir_all(&cache_dir)?;
+		for dir in [&config_dir, &path, &cache_dir] {
+			if !dir.exists() {
+				fs::create_dir_all(dir)?;
+			}
 		}
 
-		let config = Config::new(&path)?;
-		let store = Store::new(&path)?;
+		let config = Config::new(&config_dir)?;
 		let mut github_api = GitHubApi::new(endpoint);
 
+		github_api.set_retry(config.retries(), config.retry_delay());
+		github_api.set_proxy(config.proxy());
+		github_api.set_tls(config.ca_bundles(), config.system_certs());
+		github_api.set_offline(config.offline());
+
 		if let Some(token) = token.or_else(|| config.token()) {
 			github_api.set_token(token);
 		}
@@ -56,12 +91,41 @@ impl Scafalra {
 			path,
 			cache_dir,
 			config,
-			store,
+			store: OnceCell::new(),
 			github_api,
 			interactive_mode: false,
 		})
 	}
 
+	/// The store is loaded on first use, so that commands that do not need it
+	/// still work when it is broken
+	fn store(&self) -> Result<&Store> {
+		if let Some(store) = self.store.get() {
+			return Ok(store);
+		}
+
+		let store = Store::new(&self.path, &self.cache_dir).map_err(|err| {
+			let msg = format!(
+				"{:#}\nRun `sca doctor` to check and repair `{}`",
+				err,
+				Store::FILE_NAME
+			);
+			err.context(msg)
+		})?;
+
+		Ok(self.store.get_or_init(|| store))
+	}
+
+	fn store_mut(&mut self) -> Result<&mut Store> {
+		self.store()?;
+
+		Ok(self.store.get_mut().expect("the store is loaded"))
+	}
+
+	pub fn set_offline(&mut self) {
+		self.github_api.set_offline(true);
+	}
+
 	pub fn token(&mut self, args: TokenArgs) -> Result<()> {
 		debug!("args: {:#?}", args);
 
@@ -80,665 +144,3184 @@ impl Scafalra {
 		Ok(())
 	}
 
-	pub fn list(&self, args: ListArgs) {
+	pub fn rate_limit(&self) -> Result<()> {
+		let rate_limit = self.github_api.rate_limit()?;
+
+		println!("{}", rate_limit.print());
+
+		if !self.github_api.has_token() {
+			println!(
+				"No token is configured, configure one with `sca token` for a higher rate limit"
+			);
+		}
+
+		Ok(())
+	}
+
+	pub fn list(&self, args: ListArgs) -> Result<()> {
 		debug!("args: {:#?}", args);
 
 		let may_output = if args.table {
-			self.store.print_table()
+			self.store()?.print_table()
 		} else {
-			self.store.print_grid()
+			self.store()?.print_grid()
 		};
 
 		if let Some(output) = may_output {
 			println!("{}", output);
 		}
+
+		Ok(())
 	}
 
-	fn cache_template(
+	/// Download the repository and extract it to `dest`, returns the commit of
+	/// the downloaded version
+	fn fetch_repo(
 		&self,
 		repo: &Repository,
-		args: &AddArgs,
-	) -> Result<PathBuf> {
+		repo_ref: Option<&str>,
+		dest: &Path,
+	) -> Result<Option<String>> {
 		let tmp_dir = self.cache_dir.join(Self::TMP_DIR_NAME);
-		let zipball_path = self.github_api.download(repo, args, &tmp_dir)?;
-		let zipball = fs::File::open(&zipball_path)?;
-
-		let mut archive = zip::ZipArchive::new(&zipball)?;
-		archive.extract(&tmp_dir)?;
+		let zipball_path =
+			self.github_api.download(repo, repo_ref, &tmp_dir)?;
+		unzip(&zipball_path, &tmp_dir)?;
 
 		let first_dir = tmp_dir
 			.read_dir()?
 			.next()
-			.ok_or(anyhow::anyhow!("Empty directory"))??
+			.ok_or(ScafalraError::NotFound("Empty zipball".to_string()))??
 			.path();
 
 		debug!("first_dir: {:?}", first_dir);
 
+		// The root directory of a zipball is named `owner-repo-commit`
+		let commit = first_dir
+			.file_name()
+			.and_then(|name| name.to_str())
+			.and_then(|name| name.rsplit_once('-'))
+			.map(|(_, commit)| commit)
+			.filter(|commit| commit.chars().all(|c| c.is_ascii_hexdigit()))
+			.map(String::from);
+
+		if dest.exists() {
+			remove_dir_all(dest)?;
+		}
+
+		dircpy::copy_dir(first_dir, dest)?;
+
+		fs::remove_file(zipball_path)?;
+		remove_dir_all(tmp_dir)?;
+
+		Ok(commit)
+	}
+
+	fn cache_template(
+		&self,
+		repo: &Repository,
+		repo_ref: Option<&str>,
+	) -> Result<(PathBuf, Option<String>)> {
 		let template_dir = self.cache_dir.join_iter([&repo.owner, &repo.name]);
+		let commit = self.fetch_repo(repo, repo_ref, &template_dir)?;
+
+		Ok((template_dir, commit))
+	}
+
+	/// Copy a local directory or zip archive to the cache, returns the cached
+	/// directory and the default template name
+	fn cache_local(&self, src: &Path) -> Result<(PathBuf, String)> {
+		let is_zip = src.is_file();
+		let name = if is_zip {
+			src.file_stem()
+		} else {
+			src.file_name()
+		}
+		.map(|name| name.to_string_lossy().to_string())
+		.ok_or(ScafalraError::Usage(format!(
+			"Could not get a template name from `{}`",
+			src.to_string_lossy()
+		)))?;
+
+		let template_dir = self.local_cache_dir(src, &name)?;
 
 		if template_dir.exists() {
 			remove_dir_all(&template_dir)?;
 		}
 
-		dircpy::copy_dir(first_dir, &template_dir)?;
+		if is_zip {
+			let tmp_dir = self.cache_dir.join(Self::TMP_DIR_NAME);
+			unzip(src, &tmp_dir)?;
+
+			// Use the root directory if all files are placed in it
+			let mut entries = tmp_dir
+				.read_dir()?
+				.map(|entry| entry.map(|e| e.path()))
+				.collect::<Result<Vec<_>, _>>()?;
+			let root = match entries.pop() {
+				Some(entry) if entries.is_empty() && entry.is_dir() => entry,
+				_ => tmp_dir.clone(),
+			};
+
+			if let Some(parent) = template_dir.parent() {
+				fs::create_dir_all(parent)?;
+			}
+			fs::rename(root, &template_dir)?;
 
-		fs::remove_file(zipball_path)?;
-		remove_dir_all(tmp_dir)?;
+			if tmp_dir.exists() {
+				remove_dir_all(tmp_dir)?;
+			}
+		} else {
+			dircpy::copy_dir(src, &template_dir)?;
+		}
+
+		Ok((template_dir, name))
+	}
+
+	/// `@local/<name>`, or `@local/<name>-<n>` if it is used by a template
+	/// copied from another path
+	fn local_cache_dir(&self, src: &Path, name: &str) -> Result<PathBuf> {
+		let url = src.to_string_lossy();
+		let store = self.store()?;
+		let mut n = 0;
+
+		loop {
+			let key = if n == 0 {
+				name.to_string()
+			} else {
+				format!("{}-{}", name, n)
+			};
+			let dir = self.cache_dir.join_iter([Self::LOCAL_DIR_NAME, &key]);
+
+			if !store.iter().any(|(_, template)| {
+				template.path.starts_with(&dir) && template.url != url
+			}) {
+				return Ok(dir);
+			}
 
-		Ok(template_dir)
+			n += 1;
+		}
+	}
+
+	/// The repository of a cached template and the path of the template in it
+	fn template_source(
+		&self,
+		template: &Template,
+	) -> Option<(Repository, String)> {
+		let relative_path = template.path.strip_prefix(&self.cache_dir).ok()?;
+		let mut comps = relative_path
+			.components()
+			.map(|comp| comp.as_os_str().to_string_lossy().to_string());
+
+		let repo = Repository {
+			owner: comps.next()?,
+			name: comps.next()?,
+		};
+		let subdir = comps.collect::<Vec<_>>().join("/");
+
+		Some((repo, subdir))
 	}
 
 	pub fn add(&mut self, args: AddArgs) -> Result<()> {
 		debug!("args: {:#?}", args);
 
-		let repo = Repository::parse(&args.repository)?;
+		if !args.from_catalog {
+			return self.add_source(Source::from(&args));
+		}
+
+		let catalog = self.load_catalog()?;
 
-		println!("Downloading `{}` ...", args.repository);
+		let Some(entry) = catalog.get(&args.repository) else {
+			let suggestion = catalog.similar_name_suggestion(&args.repository);
+			anyhow::bail!(ScafalraError::NotFound(suggestion.to_string()));
+		};
+
+		let mut source = entry.source.clone();
+
+		if let Some(name) = args.name {
+			source.name = Some(name);
+		} else if source.depth == 0 && source.name.is_none() {
+			source.name = Some(entry.name.clone());
+		}
+
+		if !args.tags.is_empty() {
+			source.tags = args.tags;
+		} else if source.tags.is_empty() {
+			source.tags.clone_from(&entry.tags);
+		}
+
+		self.add_source(source)
+	}
+
+	/// Download or copy the repository of `source` to the cache and add its
+	/// templates to the store
+	fn add_source(&mut self, mut source: Source) -> Result<()> {
+		if source.depth > 1 {
+			anyhow::bail!(ScafalraError::Usage(
+				"The argument `depth` allows only 0 or 1".to_string()
+			));
+		}
+
+		let local_path = Path::new(&source.repo);
+
+		let (mut template_dir, commit, url, default_name) =
+			if local_path.exists() {
+				if source.repo_ref.is_some() {
+					anyhow::bail!(ScafalraError::Usage(
+						"`--branch`, `--tag` and `--commit` are not supported for a local path"
+							.to_string()
+					));
+				}
+
+				let local_path = local_path.canonicalize()?;
+
+				println!("Copying `{}` ...", source.repo);
+
+				let (template_dir, name) = self.cache_local(&local_path)?;
+				let url = local_path.to_string_lossy().to_string();
 
-		let mut template_dir = self.cache_template(&repo, &args)?;
+				source.repo.clone_from(&url);
+
+				(template_dir, None, url, name)
+			} else {
+				let repo = Repository::parse(&source.repo)?;
+
+				println!("Downloading `{}` ...", source.repo);
+
+				let (template_dir, commit) =
+					self.cache_template(&repo, source.repo_ref.as_deref())?;
+
+				(template_dir, commit, repo.url(), repo.name)
+			};
 
 		debug!("template_dir: {:?}", template_dir);
 
-		let mut template_name = args.name.unwrap_or(repo.name.clone());
+		let mut default_name = default_name;
 
-		if let Some(subdir) = args.subdir {
-			template_dir.join_canonicalize(Path::new(&subdir));
+		if let Some(subdir) = &source.subdir {
+			template_dir.join_canonicalize(Path::new(subdir));
 
 			debug!("template_dir: {:?}", template_dir);
 
 			if let Some(name) = template_dir.file_name() {
-				template_name = name.to_string_lossy().to_string();
+				default_name = name.to_string_lossy().to_string();
 			}
 		}
 
-		match args.depth.as_str() {
-			"0" => {
-				self.store.add(Template::new(
-					template_name,
-					repo.url(),
-					template_dir,
-				));
-			}
-			"1" => {
-				for entry_path in template_dir
-					.read_dir()?
-					.filter_map(|entry| entry.ok().map(|e| e.path()))
-				{
-					if entry_path.is_dir() {
-						if let Some(Some(file_name)) =
-							entry_path.file_name().map(|f| f.to_str())
-						{
-							if !file_name.starts_with('.') {
-								self.store.add(Template::new(
-									file_name,
-									repo.url(),
-									&entry_path,
-								));
-							}
+		let template_name = source.name.clone().unwrap_or(default_name);
+
+		let depth = source.depth;
+		let source = Some(source);
+
+		if depth == 0 {
+			self.store_mut()?.add(
+				Template::new(template_name, url, template_dir)
+					.with_commit(commit)
+					.with_source(source),
+			);
+		} else {
+			for entry_path in template_dir
+				.read_dir()?
+				.filter_map(|entry| entry.ok().map(|e| e.path()))
+			{
+				if entry_path.is_dir() {
+					if let Some(Some(file_name)) =
+						entry_path.file_name().map(|f| f.to_str())
+					{
+						if !file_name.starts_with('.') {
+							self.store_mut()?.add(
+								Template::new(file_name, &url, &entry_path)
+									.with_commit(commit.clone())
+									.with_source(source.clone()),
+							);
 						}
 					}
 				}
 			}
-			_ => anyhow::bail!("The argument `depth` allows only 0 or 1"),
 		}
 
-		self.store.save()?;
+		self.store_mut()?.save()?;
 
 		Ok(())
 	}
 
-	pub fn create(&self, args: CreateArgs) -> Result<()> {
-		debug!("args: {:#?}", args);
+	/// The source a template was added from, templates added by older
+	/// versions are pinned to their commit
+	fn recorded_source(&self, name: &str, template: &Template) -> Source {
+		let mut source = template.source.clone().unwrap_or_else(|| {
+			let Some((repo, subdir)) = self.template_source(template) else {
+				return Source {
+					repo: template.url.clone(),
+					..Source::default()
+				};
+			};
 
-		let tpl_name = match (&args.name, self.interactive_mode) {
-			(Some(arg_name), false) => Some(arg_name),
-			(_, true) => {
-				select(
-					self.store.all_templates_name(),
-					"Select a template:",
-					"There are no templates",
-				)?
-			}
-			_ => {
-				anyhow::bail!(
-					"Provide a name or opt for interactive mode with the `-i` argument"
-				);
+			let repo = if repo.owner == Self::LOCAL_DIR_NAME {
+				template.url.clone()
+			} else {
+				format!("{}/{}", repo.owner, repo.name)
+			};
+
+			Source {
+				repo,
+				subdir: (!subdir.is_empty()).then(|| format!("/{}", subdir)),
+				repo_ref: template.commit.clone(),
+				..Source::default()
 			}
-		};
+		});
 
-		let Some(tpl_name) = tpl_name else {
-			return Ok(());
-		};
+		// Keep the name of a renamed template
+		if source.depth == 0 {
+			source.name = Some(name.to_string());
+		}
 
-		let Some(template) = self.store.get(tpl_name) else {
-			let suggestion = self.store.similar_name_suggestion(tpl_name);
-			anyhow::bail!("{}", suggestion);
-		};
+		source
+	}
 
-		let cwd = env::current_dir()?;
+	fn export_list(&self) -> Result<SourceList> {
+		let mut list = SourceList::default();
 
-		debug!("cwd: {:?}", cwd);
+		for (name, template) in self.store()?.iter() {
+			let source = self.recorded_source(name, template);
 
-		let dest = if let Some(arg_dest) = args.destination {
-			if arg_dest.is_absolute() {
-				arg_dest
-			} else {
-				let mut ret = cwd.clone();
-				ret.join_canonicalize(&arg_dest);
-				ret
+			// Templates added with `--depth 1` share the same source
+			if !list.templates.contains(&source) {
+				list.templates.push(source);
 			}
-		} else {
-			cwd.join(tpl_name)
-		};
+		}
 
-		debug!("dest: {:?}", dest);
+		Ok(list)
+	}
 
-		let dest_display = dest.to_string_lossy();
+	pub fn export(&self) -> Result<()> {
+		let list = self.export_list()?;
 
-		if dest.exists() {
-			anyhow::bail!("`{}` is already exists", dest_display);
+		println!("{}", serde_json::to_string_pretty(&list)?);
+
+		Ok(())
+	}
+
+	/// Whether the templates of `source` are already in the store
+	fn is_present(&self, source: &Source) -> Result<bool> {
+		let store = self.store()?;
+
+		if let (0, Some(name)) = (source.depth, &source.name) {
+			if store.get(name).is_some() {
+				return Ok(true);
+			}
 		}
 
-		let sub_tpl_map: HashMap<&String, &PathBuf> = HashMap::from_iter(
-			template
-				.sub_templates
+		Ok(store.iter().any(|(name, template)| {
+			self.recorded_source(name, template).same_origin(source)
+		}))
+	}
+
+	/// Whether the template was added from `source`
+	fn is_from(
+		&self,
+		name: &str,
+		template: &Template,
+		source: &Source,
+	) -> bool {
+		self.recorded_source(name, template).same_origin(source)
+			&& (source.depth != 0
+				|| source.name.as_ref().map_or(true, |n| n == name))
+	}
+
+	pub fn sync(&mut self, args: SyncArgs) -> Result<()> {
+		debug!("args: {:#?}", args);
+
+		let content = self.read_location(&args.registry)?;
+		let list = SourceList::from_toml(&content, &args.registry)?;
+
+		let mut changed = false;
+		let mut failed = 0;
+
+		for source in &list.templates {
+			let store = self.store()?;
+
+			if store
 				.iter()
-				.map(|sub_tpl| (&sub_tpl.name, &sub_tpl.path)),
-		);
+				.any(|(name, template)| self.is_from(name, template, source))
+			{
+				continue;
+			}
 
-		let sub_tpl_names = match (&args.sub_templates, self.interactive_mode) {
-			(Some(arg_sub_tpl_names), false) => {
-				Some(arg_sub_tpl_names.iter().collect())
+			changed = true;
+
+			if let Err(err) = self.add_source(source.clone()) {
+				println!("Failed to add `{}`: {:#}", source, err);
+				failed += 1;
 			}
-			(_, true) if !template.sub_templates.is_empty() => {
-				multi_select(
-					template
-						.sub_templates
+		}
+
+		// The templates that failed to update would be pruned otherwise
+		if failed > 0 {
+			anyhow::bail!("Failed to sync {} of the templates", failed);
+		}
+
+		if args.prune {
+			let extras = self
+				.store()?
+				.iter()
+				.filter(|(name, template)| {
+					!list
+						.templates
 						.iter()
-						.map(|sub_tpl| &sub_tpl.name)
-						.collect(),
-					"Select sub templates:",
-					"There are no sub templates",
-				)?
+						.any(|source| self.is_from(name, template, source))
+				})
+				.map(|(name, _)| name.clone())
+				.collect::<Vec<_>>();
+
+			if !extras.is_empty() {
+				changed = true;
+
+				for name in extras {
+					self.store_mut()?.remove(&name)?;
+				}
+
+				self.store_mut()?.save()?;
 			}
-			_ => None,
-		};
+		}
+
+		if !changed {
+			println!("Already in sync");
+		}
+
+		Ok(())
+	}
+
+	/// Read a local file or fetch a url
+	fn read_location(&self, location: &str) -> Result<String> {
+		if location.starts_with("https://") || location.starts_with("http://") {
+			println!("Fetching `{}` ...", location);
+			self.github_api.fetch(location)
+		} else {
+			read_file(Path::new(location))
+		}
+	}
 
-		dircpy::copy_dir(&template.path, &dest)?;
+	/// Fetch the indexes of the configured catalogs, the cached index is used
+	/// if a catalog can not be fetched
+	fn load_catalog(&self) -> Result<Catalog> {
+		let locations = self.config.catalogs();
 
-		let sub_tpl_dir = dest.join(SUB_TEMPLATE_DIR);
-		if sub_tpl_dir.exists() {
-			let _ = remove_dir_all(sub_tpl_dir);
+		if locations.is_empty() {
+			anyhow::bail!(ScafalraError::Usage(format!(
+				"No catalog is configured, add the urls of the catalogs to `catalogs` in `{}`",
+				Config::FILE_NAME
+			)));
 		}
 
-		if let Some(sub_tpl_names) = sub_tpl_names {
-			for name in sub_tpl_names {
-				if let Some(sub_tpl) = sub_tpl_map.get(name) {
-					let _ = dircpy::copy_dir(sub_tpl, dest.join(name));
+		let mut catalog = Catalog::default();
+		let mut last_err = None;
+
+		// An unavailable catalog is skipped, so that the others can be used
+		for location in locations {
+			match self.load_catalog_location(location) {
+				Ok(other) => catalog.merge(other),
+				Err(err) => {
+					println!("Skipped catalog `{}`: {:#}", location, err);
+					last_err = Some(err);
 				}
 			}
 		}
 
-		println!("Created in `{}`", dest_display);
+		match last_err {
+			Some(err) if catalog.templates.is_empty() => Err(err),
+			_ => Ok(catalog),
+		}
+	}
+
+	/// Fetch the index of a catalog and cache it, the cached index is used
+	/// if it can not be fetched
+	fn load_catalog_location(&self, location: &str) -> Result<Catalog> {
+		let file_name = location
+			.chars()
+			.map(|c| {
+				if c.is_ascii_alphanumeric() {
+					c
+				} else {
+					'_'
+				}
+			})
+			.collect::<String>();
+		let cache_path = self
+			.cache_dir
+			.join_iter([Self::CATALOG_DIR_NAME, &file_name])
+			.with_extension("json");
+
+		let fetched = self.read_location(location).and_then(|content| {
+			Catalog::parse(&content, location).map(|c| (c, content))
+		});
+
+		match fetched {
+			Ok((catalog, content)) => {
+				if let Some(parent) = cache_path.parent() {
+					fs::create_dir_all(parent)?;
+				}
+				fs::write(&cache_path, content)?;
+
+				Ok(catalog)
+			}
+			Err(err) if cache_path.exists() => {
+				println!(
+					"Failed to fetch catalog `{}`, using the cached index: {:#}",
+					location, err
+				);
+
+				Catalog::parse(&fs::read_to_string(&cache_path)?, location)
+			}
+			Err(err) => Err(err),
+		}
+	}
+
+	pub fn search(&self, args: SearchArgs) -> Result<()> {
+		debug!("args: {:#?}", args);
+
+		let catalog = self.load_catalog()?;
+		let entries = catalog.search(&args.query);
+
+		match print_entries(&entries) {
+			Some(output) => println!("{}", output),
+			None => println!("No template matches `{}`", args.query),
+		}
 
 		Ok(())
 	}
 
-	pub fn rename(&mut self, args: RenameArgs) -> Result<()> {
+	pub fn catalog(&self) -> Result<()> {
+		let catalog = self.load_catalog()?;
+		let entries = catalog.templates.iter().collect::<Vec<_>>();
+
+		if let Some(output) = print_entries(&entries) {
+			println!("{}", output);
+		}
+
+		Ok(())
+	}
+
+	pub fn import(&mut self, args: ImportArgs) -> Result<()> {
 		debug!("args: {:#?}", args);
 
-		let (name, new_name) = match (
-			args.name,
-			args.new_name,
-			self.interactive_mode,
-		) {
-			(Some(name), Some(new_name), false) => (name, new_name),
-			(_, _, true) => {
-				let name = select(
-					self.store.all_templates_name(),
-					"Select a template:",
-					"There are no templates",
-				)?;
-				let Some(name) = name else {
-					return Ok(());
-				};
-				let Some(new_name) = input("New name?")? else {
-					return Ok(());
-				};
-				(name.clone(), new_name)
-			}
-			(Some(_), None, false) => {
-				anyhow::bail!("Please provide a new name");
+		let list = SourceList::read(&args.file)?;
+		let (mut added, mut skipped, mut failed) = (0, 0, 0);
+
+		for source in list.templates {
+			if self.is_present(&source)? {
+				println!("Skipping `{}`, it is already present", source);
+				skipped += 1;
+				continue;
 			}
-			_ => {
-				anyhow::bail!(
-					"Provide both the target and new name, or opt for interactive mode with the `-i` argument"
-				);
+
+			match self.add_source(source.clone()) {
+				Ok(()) => added += 1,
+				Err(err) => {
+					println!("Failed to add `{}`: {:#}", source, err);
+					failed += 1;
+				}
 			}
-		};
+		}
 
-		let renamed = self.store.rename(&name, &new_name);
+		println!("{} added, {} skipped, {} failed", added, skipped, failed);
 
-		if renamed {
-			self.store.save()?;
-			println!("{} -> {}", name, new_name);
+		if failed > 0 {
+			anyhow::bail!("Failed to import {} of the templates", failed);
 		}
 
 		Ok(())
 	}
 
-	pub fn remove(&mut self, args: RemoveArgs) -> Result<()> {
+	pub fn create(&mut self, args: CreateArgs) -> Result<()> {
 		debug!("args: {:#?}", args);
 
-		let names = match (args.names, self.interactive_mode) {
-			(Some(names), false) => Some(names),
-			(_, true) => {
-				multi_select(
-					self.store.all_templates_name(),
-					"Select templates:",
-					"There are no templates",
-				)?
-				.map(|vs| vs.into_iter().cloned().collect())
+		let is_remote = match &args.name {
+			Some(name) if !self.interactive_mode => {
+				name.contains('/') && self.store()?.get(name).is_none()
 			}
+			_ => false,
+		};
+
+		if is_remote {
+			return self.create_remote(args);
+		}
+
+		if args.repo_ref.is_some() {
+			anyhow::bail!(ScafalraError::Usage(
+				"`--ref` can only be used when creating from `owner/repo[/subdir]`, use `sca add --ref` to change the ref of a template"
+					.to_string()
+			));
+		}
+
+		if args.save {
+			anyhow::bail!(ScafalraError::Usage(
+				"`--save` can only be used when creating from `owner/repo[/subdir]`"
+					.to_string()
+			));
+		}
+
+		let tpl_name = match (&args.name, self.interactive_mode) {
+			(Some(arg_name), false) => Some(arg_name),
+			(_, true) => self.select_template()?,
 			_ => {
-				anyhow::bail!(
-					"Provide names or opt for interactive mode with the `-i` argument"
-				);
+				anyhow::bail!(ScafalraError::Usage(
+					"Provide a name or opt for interactive mode with the `-i` argument"
+						.to_string()
+				));
 			}
 		};
 
-		let Some(names) = names else {
+		let Some(tpl_name) = tpl_name.cloned() else {
 			return Ok(());
 		};
 
-		for name in names {
-			self.store.remove(&name)?;
-		}
+		let Some(template) = self.store()?.get(&tpl_name) else {
+			let suggestion = self.store()?.similar_name_suggestion(&tpl_name);
+			anyhow::bail!(ScafalraError::NotFound(suggestion.to_string()));
+		};
 
-		self.store.save()?;
+		let subdir = self
+			.template_source(template)
+			.map(|(_, subdir)| subdir)
+			.unwrap_or_default();
 
-		Ok(())
+		self.create_from(&tpl_name, template, subdir, args)
 	}
-}
 
-#[cfg(test)]
-mod test_utils {
-	use std::fs;
+	/// Create from `owner/repo[/subdir]`, the download is discarded afterward
+	/// unless `--save` is used
+	fn create_remote(&mut self, args: CreateArgs) -> Result<()> {
+		let input = args.name.clone().unwrap_or_default();
+		let (repo, subdir) = Repository::parse_with_subdir(&input)?;
+		let name = subdir
+			.as_deref()
+			.and_then(|subdir| subdir.rsplit('/').next())
+			.unwrap_or(&repo.name)
+			.to_string();
+
+		// A dry run writes nothing, not even the store
+		if args.save && !args.dry_run {
+			self.add_source(Source {
+				repo: format!("{}/{}", repo.owner, repo.name),
+				subdir: subdir.clone(),
+				repo_ref: args.repo_ref.clone(),
+				name: Some(name.clone()),
+				..Source::default()
+			})?;
+
+			let template =
+				self.store()?.get(&name).expect("the template is added");
+
+			return self.create_from(
+				&name,
+				template,
+				subdir.unwrap_or_default(),
+				args,
+			);
+		}
 
-	use mockito::{Mock, ServerGuard};
-	use tempfile::{tempdir, TempDir};
+		println!("Downloading `{}` ...", input);
 
-	use super::Scafalra;
-	use crate::{
-		store::{test_utils::StoreJsonMock, Store},
-		sub_template::test_utils::sub_tempaltes_dir_setup,
-	};
+		// Unique for each run, so that parallel runs don't collide
+		let remote_dir = self.cache_dir.join_iter([
+			Self::REMOTE_DIR_NAME,
+			&std::process::id().to_string(),
+		]);
 
-	pub struct ScafalraMock {
-		pub scafalra: Scafalra,
-		pub tmp_dir: TempDir,
-		endpoint_cache: Option<String>,
+		let ret = (|| {
+			let commit =
+				self.fetch_repo(&repo, args.repo_ref.as_deref(), &remote_dir)?;
+
+			let mut template_dir = remote_dir.clone();
+
+			if let Some(subdir) = &subdir {
+				template_dir.join_canonicalize(Path::new(subdir));
+
+				if !template_dir.is_dir() {
+					anyhow::bail!(ScafalraError::NotFound(format!(
+						"No such directory `{}` in `{}/{}`",
+						subdir, repo.owner, repo.name
+					)));
+				}
+			}
+
+			let template = Template::new(&name, repo.url(), template_dir)
+				.with_commit(commit);
+
+			self.create_from(&name, &template, subdir.unwrap_or_default(), args)
+		})();
+
+		if remote_dir.exists() {
+			remove_dir_all(&remote_dir)?;
+		}
+
+		ret
 	}
 
-	impl ScafalraMock {
-		pub fn new() -> Self {
-			let tmp_dir = tempdir().unwrap();
-			let scafalra = Scafalra::new(
-				tmp_dir.path().join("scafalra"),
-				None,
-				Some("token"),
-			)
-			.unwrap();
+	/// The templates `template` extends in the order they are created, bases
+	/// first and `template` itself last
+	fn layers<'a>(
+		&'a self,
+		name: &'a str,
+		template: &'a Template,
+	) -> Result<Vec<&'a Template>> {
+		let mut layers = Vec::new();
+		self.collect_layers(name, template, &mut Vec::new(), &mut layers)?;
+
+		Ok(layers)
+	}
 
-			Self {
-				scafalra,
-				tmp_dir,
-				endpoint_cache: None,
+	fn collect_layers<'a>(
+		&'a self,
+		name: &'a str,
+		template: &'a Template,
+		stack: &mut Vec<(&'a str, &'a Template)>,
+		layers: &mut Vec<&'a Template>,
+	) -> Result<()> {
+		// A base that is extended more than once is created only once
+		if layers.iter().any(|layer| std::ptr::eq(*layer, template)) {
+			return Ok(());
+		}
+
+		if let Some(pos) = stack
+			.iter()
+			.position(|(_, tpl)| std::ptr::eq(*tpl, template))
+		{
+			let cycle = stack[pos..]
+				.iter()
+				.map(|(name, _)| *name)
+				.chain([name])
+				.collect::<Vec<_>>()
+				.join("` -> `");
+			anyhow::bail!(ScafalraError::Usage(format!(
+				"Templates can not extend each other: `{}`",
+				cycle
+			)));
+		}
+
+		let manifest = Manifest::load(&template.path)?;
+
+		stack.push((name, template));
+
+		for base_name in manifest.extends {
+			let store = self.store()?;
+
+			let Some((base_name, base)) = store.get_key_value(&base_name)
+			else {
+				let suggestion = store.similar_name_suggestion(&base_name);
+				anyhow::bail!(ScafalraError::NotFound(format!(
+					"{}\nIt is extended by template `{}`",
+					suggestion, name
+				)));
+			};
+
+			self.collect_layers(base_name, base, stack, layers)?;
+		}
+
+		stack.pop();
+		layers.push(template);
+
+		Ok(())
+	}
+
+	/// Copy `template` to the destination, `subdir` is the path of the
+	/// template in its repository
+	fn create_from(
+		&self,
+		tpl_name: &str,
+		template: &Template,
+		subdir: String,
+		args: CreateArgs,
+	) -> Result<()> {
+		let cwd = env::current_dir()?;
+
+		debug!("cwd: {:?}", cwd);
+
+		let dest = if let Some(arg_dest) = args.destination {
+			if arg_dest.is_absolute() {
+				arg_dest
+			} else {
+				let mut ret = cwd.clone();
+				ret.join_canonicalize(&arg_dest);
+				ret
 			}
+		} else {
+			cwd.join(tpl_name)
+		};
+
+		debug!("dest: {:?}", dest);
+
+		let dest_display = dest.to_string_lossy();
+
+		if dest.exists() && !args.dry_run && !args.merge {
+			anyhow::bail!(ScafalraError::Conflict(format!(
+				"`{}` is already exists, use `--merge` to create into it",
+				dest_display
+			)));
 		}
 
-		pub fn endpoint(self, endpoint: &str) -> Self {
-			let scafalra = Scafalra::new(
-				self.scafalra.path,
-				Some(endpoint),
-				Some("token"),
-			)
-			.unwrap();
+		let metas =
+			read_sub_template_metas(&template.path, &template.sub_templates)?;
 
-			Self {
-				scafalra,
-				endpoint_cache: Some(endpoint.to_string()),
-				..self
+		let sub_tpl_names = match (&args.sub_templates, self.interactive_mode) {
+			(Some(arg_sub_tpl_names), false) => arg_sub_tpl_names.clone(),
+			(_, true) if !template.sub_templates.is_empty() => {
+				select_sub_templates(&template.sub_templates, &metas, None)?
+			}
+			_ => Vec::new(),
+		};
+
+		let sub_tpl_names =
+			resolve_selection(&template.sub_templates, &sub_tpl_names, &metas)?;
+
+		let layers = self.layers(tpl_name, template)?;
+		let plan = plan_template(
+			&layers
+				.iter()
+				.map(|tpl| tpl.path.as_path())
+				.collect::<Vec<_>>(),
+			&template.sub_templates,
+			&sub_tpl_names,
+			&metas,
+		)?;
+
+		if args.dry_run {
+			println!("Dry run, nothing will be written to `{}`", dest_display);
+			println!("{}", plan.print());
+			if let Some(conflicts) = plan.print_conflicts(&dest) {
+				println!("{}", conflicts);
 			}
+			return Ok(());
 		}
 
-		/// create a template that name is `bar`
-		pub fn with_content(self) -> Self {
-			use crate::path_ext::*;
+		let answers = Answers {
+			template: tpl_name.to_string(),
+			url: template.url.clone(),
+			subdir,
+			commit: template.commit.clone(),
+			sub_templates: sub_tpl_names,
+		};
 
-			let store_file = self.scafalra.path.join(Store::FILE_NAME);
-			let bar_dir = self.scafalra.path.join_iter([
-				Scafalra::CACHE_DIR_NAME,
-				"foo",
-				"bar",
-			]);
-			fs::create_dir_all(&bar_dir).unwrap();
-			sub_tempaltes_dir_setup(&bar_dir, &["dir-1", "dir-2"]);
-			fs::write(bar_dir.join("baz.txt"), "").unwrap();
-			fs::write(
-				store_file,
-				StoreJsonMock::new().push("bar", &bar_dir).build(),
-			)
-			.unwrap();
-			let scafalra = Scafalra::new(
-				self.scafalra.path,
-				self.endpoint_cache.as_deref(),
-				Some("token"),
-			)
-			.unwrap();
+		// Nothing is written to `dest` until everything is staged, the staging
+		// directory is removed if anything fails
+		let staging = Staging::new(&dest)?;
+
+		if args.merge {
+			let report = plan.merge(&dest, &staging.path, |path| {
+				resolve_conflict(args.conflict, path)
+			})?;
+			// The project keeps recording the template it was created from,
+			// which `upgrade` uses
+			if !dest.join(Answers::FILE_NAME).exists() {
+				answers.write(&staging.path)?;
+			}
+			staging.commit_merge()?;
+			println!("{}", report.print());
+			println!("Merged into `{}`", dest_display);
+			return Ok(());
+		}
 
-			Self {
-				scafalra,
-				..self
+		plan.execute(&staging.path)?;
+		answers.write(&staging.path)?;
+		staging.commit()?;
+
+		println!("Created in `{}`", dest_display);
+
+		Ok(())
+	}
+
+	pub fn upgrade(&self, args: UpgradeArgs) -> Result<()> {
+		debug!("args: {:#?}", args);
+
+		let cwd = env::current_dir()?;
+
+		let project_dir = match args.directory {
+			Some(arg_dir) if arg_dir.is_absolute() => arg_dir,
+			Some(arg_dir) => {
+				let mut ret = cwd;
+				ret.join_canonicalize(&arg_dir);
+				ret
+			}
+			None => cwd,
+		};
+
+		debug!("project_dir: {:?}", project_dir);
+
+		let mut answers = Answers::read(&project_dir)?;
+
+		let Some(template) = self.store()?.get(&answers.template) else {
+			let suggestion =
+				self.store()?.similar_name_suggestion(&answers.template);
+			anyhow::bail!(ScafalraError::NotFound(suggestion.to_string()));
+		};
+
+		let Some(base_commit) = answers.commit.clone() else {
+			anyhow::bail!(ScafalraError::NotFound(
+				"The version of the template used by the project is unknown"
+					.to_string()
+			));
+		};
+
+		let Some(new_commit) = template.commit.clone() else {
+			anyhow::bail!(ScafalraError::NotFound(format!(
+				"The version of template `{}` is unknown, please add it again",
+				template.name
+			)));
+		};
+
+		if base_commit == new_commit {
+			println!("Already up to date");
+			return Ok(());
+		}
+
+		let Some((repo, _)) = self.template_source(template) else {
+			anyhow::bail!(ScafalraError::NotFound(format!(
+				"Could not find the repository of template `{}`",
+				template.name
+			)));
+		};
+
+		println!(
+			"Downloading `{}/{}` at `{}` ...",
+			repo.owner, repo.name, base_commit
+		);
+
+		let base_dir = self.cache_dir.join_iter([
+			Self::UPGRADE_DIR_NAME,
+			&std::process::id().to_string(),
+		]);
+
+		let ret = (|| {
+			self.fetch_repo(&repo, Some(&base_commit), &base_dir)?;
+
+			let mut base_template_dir = base_dir.clone();
+			base_template_dir.join_canonicalize(Path::new(&answers.subdir));
+
+			let layers = self
+				.layers(&answers.template, template)?
+				.iter()
+				.map(|tpl| tpl.path.as_path())
+				.collect::<Vec<_>>();
+
+			let new = plan_template(
+				&layers,
+				&template.sub_templates,
+				&answers.sub_templates,
+				&read_sub_template_metas(
+					&template.path,
+					&template.sub_templates,
+				)?,
+			)?;
+
+			// The base version may extend other templates
+			let base_template = Template::new(
+				&answers.template,
+				&template.url,
+				&base_template_dir,
+			);
+			let layers = self
+				.layers(&answers.template, &base_template)?
+				.iter()
+				.map(|tpl| tpl.path.as_path())
+				.collect::<Vec<_>>();
+
+			let base_sub_templates = read_sub_templates(&base_template_dir);
+			let base = plan_template(
+				&layers,
+				&base_sub_templates,
+				&answers.sub_templates,
+				&read_sub_template_metas(
+					&base_template_dir,
+					&base_sub_templates,
+				)?,
+			)?;
+
+			upgrade(&base, &new, &project_dir)
+		})();
+
+		if base_dir.exists() {
+			remove_dir_all(&base_dir)?;
+		}
+
+		let report = ret?;
+
+		answers.commit = Some(new_commit);
+		answers.write(&project_dir)?;
+
+		println!("{}", report.print());
+
+		if !report.conflicted.is_empty() {
+			println!("Please resolve the conflicts manually");
+		}
+
+		Ok(())
+	}
+
+	pub fn rename(&mut self, args: RenameArgs) -> Result<()> {
+		debug!("args: {:#?}", args);
+
+		let (name, new_name) = match (
+			args.name,
+			args.new_name,
+			self.interactive_mode,
+		) {
+			(Some(name), Some(new_name), false) => (name, new_name),
+			(_, _, true) => {
+				let name = self.select_template()?;
+				let Some(name) = name else {
+					return Ok(());
+				};
+				let Some(new_name) = input("New name?")? else {
+					return Ok(());
+				};
+				(name.clone(), new_name)
+			}
+			(Some(_), None, false) => {
+				anyhow::bail!(ScafalraError::Usage(
+					"Please provide a new name".to_string()
+				));
+			}
+			_ => {
+				anyhow::bail!(ScafalraError::Usage(
+					"Provide both the target and new name, or opt for interactive mode with the `-i` argument"
+						.to_string()
+				));
+			}
+		};
+
+		self.store_mut()?.rename(&name, &new_name)?;
+		self.store_mut()?.save()?;
+		println!("{} -> {}", name, new_name);
+
+		Ok(())
+	}
+
+	pub fn show(&self, args: ShowArgs) -> Result<()> {
+		debug!("args: {:#?}", args);
+
+		let tpl_name = match (&args.name, self.interactive_mode) {
+			(Some(arg_name), false) => Some(arg_name),
+			(_, true) => self.select_template()?,
+			_ => {
+				anyhow::bail!(ScafalraError::Usage(
+					"Provide a name or opt for interactive mode with the `-i` argument"
+						.to_string()
+				));
+			}
+		};
+
+		let Some(tpl_name) = tpl_name else {
+			return Ok(());
+		};
+
+		let Some(template) = self.store()?.get(tpl_name) else {
+			let suggestion = self.store()?.similar_name_suggestion(tpl_name);
+			anyhow::bail!(ScafalraError::NotFound(suggestion.to_string()));
+		};
+
+		println!("{}", render_show(template, args.path.as_deref())?);
+
+		Ok(())
+	}
+
+	fn select_template(&self) -> Result<Option<&String>> {
+		select_with_preview(
+			self.store()?.all_templates_name(),
+			"Select a template:",
+			"There are no templates",
+			|name| self.template_preview(name),
+		)
+	}
+
+	fn template_preview(&self, name: &str) -> String {
+		let Some(template) = self.store.get().and_then(|store| store.get(name))
+		else {
+			return String::new();
+		};
+
+		let description = match Manifest::load(&template.path) {
+			Ok(manifest) => {
+				manifest
+					.description
+					.unwrap_or_else(|| "No description".to_string())
+			}
+			Err(err) => err.to_string(),
+		};
+
+		let tree = render_tree(&template.path, Some(1))
+			.unwrap_or_else(|err| err.to_string());
+
+		format!("{}\n\n{}", description, tree)
+	}
+
+	pub fn remove(&mut self, args: RemoveArgs) -> Result<()> {
+		debug!("args: {:#?}", args);
+
+		let names = match (args.names, self.interactive_mode) {
+			(Some(names), false) => Some(names),
+			(_, true) => {
+				multi_select(
+					self.store()?.all_templates_name(),
+					"Select templates:",
+					"There are no templates",
+				)?
+				.map(|vs| vs.into_iter().cloned().collect())
+			}
+			_ => {
+				anyhow::bail!(ScafalraError::Usage(
+					"Provide names or opt for interactive mode with the `-i` argument"
+						.to_string()
+				));
 			}
+		};
+
+		let Some(names) = names else {
+			return Ok(());
+		};
+
+		// Nothing is removed if any of the names does not exist
+		let store = self.store()?;
+		if let Some(name) = names.iter().find(|name| store.get(name).is_none())
+		{
+			anyhow::bail!(ScafalraError::NotFound(
+				store.similar_name_suggestion(name).to_string()
+			));
 		}
+
+		for name in names {
+			self.store_mut()?.remove(&name)?;
+		}
+
+		self.store_mut()?.save()?;
+
+		Ok(())
 	}
 
-	pub struct ServerMock {
-		pub server: ServerGuard,
-		pub download_mock: Mock,
+	pub fn doctor(&mut self, args: DoctorArgs) -> Result<()> {
+		debug!("args: {:#?}", args);
+
+		Store::check_version(&self.path)?;
+
+		match Store::new(&self.path, &self.cache_dir) {
+			Ok(mut store) if !args.rebuild => {
+				if store.prune_missing().is_empty() {
+					println!("`{}` is healthy", Store::FILE_NAME);
+				} else {
+					println!("Removing templates whose directory is missing");
+					store.save()?;
+				}
+
+				return Ok(());
+			}
+			Ok(_) => (),
+			Err(err) => {
+				println!("Failed to load `{}`: {:#}", Store::FILE_NAME, err);
+			}
+		}
+
+		if let Some(path) = Store::set_aside(&self.path)? {
+			println!(
+				"The previous file is kept as `{}`",
+				path.to_string_lossy()
+			);
+		}
+
+		if !args.rebuild && Store::restore_backup(&self.path)? {
+			println!("Restored `{}` from the backup", Store::FILE_NAME);
+			return Ok(());
+		}
+
+		println!("Rebuilding `{}` from the cache", Store::FILE_NAME);
+
+		let mut store = Store::new(&self.path, &self.cache_dir)?;
+
+		for template in self.scan_cache()? {
+			store.add(template);
+		}
+
+		store.save()?;
+
+		Ok(())
+	}
+
+	/// The repositories and local paths in the cache, templates that were
+	/// added with `--subdir` or `--depth 1` are restored as the whole
+	/// repository
+	fn scan_cache(&self) -> Result<Vec<Template>> {
+		let mut templates: Vec<Template> = Vec::new();
+
+		for owner_dir in sub_dirs(&self.cache_dir)? {
+			let owner = owner_dir
+				.file_name()
+				.unwrap_or_default()
+				.to_string_lossy()
+				.to_string();
+
+			if [
+				Self::TMP_DIR_NAME,
+				Self::UPGRADE_DIR_NAME,
+				Self::REMOTE_DIR_NAME,
+				Self::CATALOG_DIR_NAME,
+			]
+			.contains(&owner.as_str())
+			{
+				continue;
+			}
+
+			for repo_dir in sub_dirs(&owner_dir)? {
+				let name = repo_dir
+					.file_name()
+					.unwrap_or_default()
+					.to_string_lossy()
+					.to_string();
+
+				let url = if owner == Self::LOCAL_DIR_NAME {
+					repo_dir.to_string_lossy().to_string()
+				} else {
+					Repository {
+						owner: owner.clone(),
+						name: name.clone(),
+					}
+					.url()
+				};
+
+				let name = if templates.iter().any(|tpl| tpl.name == name) {
+					format!("{}-{}", owner, name)
+				} else {
+					name
+				};
+
+				templates.push(Template::new(name, url, &repo_dir));
+			}
+		}
+
+		Ok(templates)
+	}
+}
+
+/// The sub directories of `dir` in order
+fn sub_dirs(dir: &Path) -> Result<Vec<PathBuf>> {
+	let mut dirs = Vec::new();
+
+	for entry in dir.read_dir()? {
+		let path = entry?.path();
+
+		if path.is_dir() {
+			dirs.push(path);
+		}
+	}
+
+	dirs.sort();
+
+	Ok(dirs)
+}
+
+/// Extract the zip archive at `zip_path` to `dest` with a progress bar
+fn unzip(zip_path: &Path, dest: &Path) -> Result<()> {
+	let zip_file = fs::File::open(zip_path)?;
+	let mut archive = zip::ZipArchive::new(&zip_file)?;
+
+	let bar = progress::extract_bar(archive.len() as u64);
+	let ret = extract(&mut archive, dest, &bar);
+	bar.finish_and_clear();
+
+	ret
+}
+
+/// Same as `ZipArchive::extract`, advancing `bar` by each extracted file
+fn extract(
+	archive: &mut zip::ZipArchive<&fs::File>,
+	dest: &Path,
+	bar: &ProgressBar,
+) -> Result<()> {
+	for idx in 0..archive.len() {
+		let mut file = archive.by_index(idx)?;
+		let out_path = file.enclosed_name().map(|name| dest.join(name)).ok_or(
+			zip::result::ZipError::InvalidArchive("Invalid file path"),
+		)?;
+
+		if file.is_dir() {
+			fs::create_dir_all(&out_path)?;
+		} else {
+			if let Some(parent) = out_path.parent() {
+				fs::create_dir_all(parent)?;
+			}
+			let mut out_file = fs::File::create(&out_path)?;
+			io::copy(&mut file, &mut out_file)?;
+		}
+
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::PermissionsExt;
+
+			if let Some(mode) = file.unix_mode() {
+				fs::set_permissions(
+					&out_path,
+					fs::Permissions::from_mode(mode),
+				)?;
+			}
+		}
+
+		bar.inc(1);
+	}
+
+	Ok(())
+}
+
+/// The pipeline of `create`, the template itself followed by the selected
+/// sub templates
+/// `layers` are the directories of the templates it extends and the template
+/// itself, later layers override the files of earlier ones
+fn plan_template(
+	layers: &[&Path],
+	sub_templates: &[SubTemplate],
+	sub_tpl_names: &[String],
+	metas: &BTreeMap<String, SubTemplateMeta>,
+) -> Result<Plan> {
+	let mut plan = Plan::new();
+
+	for layer in layers {
+		plan.add_dir(layer, Path::new(""))?;
+	}
+
+	for name in sub_tpl_names {
+		if let Some(sub_tpl) =
+			sub_templates.iter().find(|sub_tpl| &sub_tpl.name == name)
+		{
+			let target = match metas.get(name) {
+				Some(meta) => meta.target(name)?,
+				None => PathBuf::from(name),
+			};
+			plan.add_dir(&sub_tpl.path, &target)?;
+		}
+	}
+
+	Ok(plan)
+}
+
+/// Select the sub templates under `parent`, and then the nested ones of each
+/// selected sub template
+fn select_sub_templates(
+	sub_templates: &[SubTemplate],
+	metas: &BTreeMap<String, SubTemplateMeta>,
+	parent: Option<&str>,
+) -> Result<Vec<String>> {
+	let choices = sub_templates
+		.iter()
+		.filter(|sub_tpl| sub_tpl.parent.as_deref() == parent)
+		.map(|sub_tpl| {
+			SubTemplateChoice {
+				name: sub_tpl.dir_name(),
+				full_name: &sub_tpl.name,
+				description: metas
+					.get(&sub_tpl.name)
+					.and_then(|meta| meta.description.as_deref()),
+			}
+		})
+		.collect::<Vec<_>>();
+
+	if choices.is_empty() {
+		return Ok(Vec::new());
+	}
+
+	let prompt = match parent {
+		Some(parent) => format!("Select sub templates of `{}`:", parent),
+		None => "Select sub templates:".to_string(),
+	};
+	let selected =
+		multi_select(choices, &prompt, "There are no sub templates")?
+			.unwrap_or_default();
+
+	let mut names = Vec::new();
+
+	for choice in selected {
+		names.push(choice.full_name.to_string());
+		names.extend(select_sub_templates(
+			sub_templates,
+			metas,
+			Some(choice.full_name),
+		)?);
+	}
+
+	Ok(names)
+}
+
+/// A sub template in the interactive selection
+struct SubTemplateChoice<'a> {
+	name: &'a str,
+	full_name: &'a str,
+	description: Option<&'a str>,
+}
+
+impl std::fmt::Display for SubTemplateChoice<'_> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.name)?;
+
+		if let Some(description) = self.description {
+			write!(f, " - {}", description)?;
+		}
+
+		Ok(())
+	}
+}
+
+fn resolve_conflict(policy: ConflictPolicy, path: &Path) -> Result<Resolution> {
+	let resolution = match policy {
+		ConflictPolicy::Skip => Resolution::Skip,
+		ConflictPolicy::Overwrite => Resolution::Overwrite,
+		ConflictPolicy::KeepBoth => Resolution::KeepBoth,
+		ConflictPolicy::Prompt => {
+			select(
+				vec![
+					Resolution::Skip,
+					Resolution::Overwrite,
+					Resolution::KeepBoth,
+				],
+				&format!("`{}` already exists:", path.to_string_lossy()),
+				"",
+			)?
+			.unwrap_or(Resolution::Skip)
+		}
+	};
+
+	Ok(resolution)
+}
+
+fn render_show(template: &Template, path: Option<&Path>) -> Result<String> {
+	let mut target = template.path.clone();
+
+	if let Some(path) = path {
+		if path.components().any(|comp| {
+			!matches!(comp, Component::Normal(_) | Component::CurDir)
+		}) {
+			anyhow::bail!(ScafalraError::Usage(format!(
+				"`{}` is not a relative path in template `{}`",
+				path.to_string_lossy(),
+				template.name
+			)));
+		}
+
+		target.join_canonicalize(path);
+	}
+
+	debug!("target: {:?}", target);
+
+	// A symlink in the template may point outside of it
+	if let (Ok(canonical), Ok(root)) =
+		(target.canonicalize(), template.path.canonicalize())
+	{
+		if !canonical.starts_with(root) {
+			anyhow::bail!(ScafalraError::Usage(format!(
+				"`{}` is outside of template `{}`",
+				path.unwrap_or(Path::new("")).to_string_lossy(),
+				template.name
+			)));
+		}
+	}
+
+	if target.is_file() {
+		let content = fs::read(&target)?;
+
+		return Ok(match String::from_utf8(content) {
+			Ok(text) => text.trim_end_matches('\n').to_string(),
+			Err(err) => format!("Binary file, {} bytes", err.as_bytes().len()),
+		});
+	}
+
+	if !target.is_dir() {
+		anyhow::bail!(ScafalraError::NotFound(format!(
+			"No such file or directory `{}` in template `{}`",
+			path.unwrap_or(Path::new("")).to_string_lossy(),
+			template.name
+		)));
+	}
+
+	let root = match path {
+		Some(path) if path.components().next().is_some() => {
+			path.to_string_lossy().to_string()
+		}
+		_ => template.name.clone(),
+	};
+
+	let tree = render_tree(&target, None)?;
+
+	if tree.is_empty() {
+		return Ok(root.blue());
+	}
+
+	Ok(format!("{}\n{}", root.blue(), tree))
+}
+
+#[cfg(test)]
+mod test_utils {
+	use std::fs;
+
+	use mockito::{Mock, ServerGuard};
+	use tempfile::{tempdir, TempDir};
+
+	use super::Scafalra;
+	use crate::{
+		dirs::Dirs,
+		store::{test_utils::StoreJsonMock, Store},
+		sub_template::test_utils::sub_tempaltes_dir_setup,
+	};
+
+	pub struct ScafalraMock {
+		pub scafalra: Scafalra,
+		pub tmp_dir: TempDir,
+		endpoint_cache: Option<String>,
+	}
+
+	impl ScafalraMock {
+		pub fn new() -> Self {
+			let tmp_dir = tempdir().unwrap();
+			let scafalra = Scafalra::new(
+				Dirs::single(tmp_dir.path().join("scafalra")),
+				None,
+				Some("token"),
+			)
+			.unwrap();
+
+			Self {
+				scafalra,
+				tmp_dir,
+				endpoint_cache: None,
+			}
+		}
+
+		pub fn endpoint(self, endpoint: &str) -> Self {
+			let scafalra = Scafalra::new(
+				Dirs::single(self.scafalra.path),
+				Some(endpoint),
+				Some("token"),
+			)
+			.unwrap();
+
+			Self {
+				scafalra,
+				endpoint_cache: Some(endpoint.to_string()),
+				..self
+			}
+		}
+
+		/// create a template that name is `bar`
+		pub fn with_content(self) -> Self {
+			use crate::path_ext::*;
+
+			let store_file = self.scafalra.path.join(Store::FILE_NAME);
+			let bar_dir = self.scafalra.cache_dir.join_iter(["foo", "bar"]);
+			fs::create_dir_all(&bar_dir).unwrap();
+			sub_tempaltes_dir_setup(&bar_dir, &["dir-1", "dir-2"]);
+			fs::write(bar_dir.join("baz.txt"), "").unwrap();
+			fs::write(
+				store_file,
+				StoreJsonMock::new().push("bar", &bar_dir).build(),
+			)
+			.unwrap();
+			let scafalra = Scafalra::new(
+				Dirs::single(self.scafalra.path),
+				self.endpoint_cache.as_deref(),
+				Some("token"),
+			)
+			.unwrap();
+
+			Self {
+				scafalra,
+				..self
+			}
+		}
+	}
+
+	/// Build a zipball like the one GitHub returns, all files are placed in
+	/// the `root` directory
+	pub fn zipball(root: &str, files: &[(&str, &str)]) -> Vec<u8> {
+		use std::io::{Cursor, Write};
+
+		use zip::{write::FileOptions, ZipWriter};
+
+		let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+		let options = FileOptions::default();
+
+		writer.add_directory(root, options).unwrap();
+
+		for (name, content) in files {
+			writer
+				.start_file(format!("{}/{}", root, name), options)
+				.unwrap();
+			writer.write_all(content.as_bytes()).unwrap();
+		}
+
+		writer.finish().unwrap().into_inner()
+	}
+
+	pub struct ServerMock {
+		pub server: ServerGuard,
+		pub download_mock: Mock,
+	}
+
+	impl ServerMock {
+		pub fn new() -> Self {
+			let mut server = mockito::Server::new();
+
+			let download_mock = server
+				.mock("GET", "/repos/foo/bar/zipball")
+				.with_status(200)
+				.with_header("content-type", "application/zip")
+				.with_body_from_file("fixtures/scafalra-test.zip")
+				.create();
+
+			Self {
+				server,
+				download_mock,
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{collections::BTreeMap, fs, path::Path};
+
+	use anyhow::Result;
+	use similar_asserts::assert_eq;
+
+	use super::{
+		plan_template, render_show,
+		test_utils::{zipball, ScafalraMock, ServerMock},
+		Scafalra,
+	};
+	use crate::{
+		answers::Answers,
+		cli::{
+			test_utils::{AddArgsMock, CreateArgsMock},
+			ConflictPolicy, DoctorArgs, ImportArgs, ListArgs, RemoveArgs,
+			RenameArgs, SyncArgs, TokenArgs, UpgradeArgs,
+		},
+		config::Config,
+		dirs::Dirs,
+		error::{exit_code, ScafalraError},
+		manifest::test_utils::manifest_setup,
+		path_ext::*,
+		source::{Source, SourceList},
+		staging::test_utils::interrupt_at,
+		store::{test_utils::StoreJsonMock, Store},
+		sub_template::SUB_TEMPLATE_DIR,
+		template::Template,
+	};
+
+	const FIXTURE_COMMIT: &str = "9dad271040b346eb4c1c767e28d83b2c488f3b2f";
+
+	#[test]
+	fn test_scafalra_new() {
+		let ScafalraMock {
+			tmp_dir: _tmp_dir,
+			scafalra,
+			..
+		} = ScafalraMock::new();
+
+		assert!(scafalra.cache_dir.exists());
+		assert!(scafalra.store().unwrap().path.exists());
+		assert!(scafalra.config.path.exists());
+	}
+
+	#[test]
+	fn test_scafalra_new_invalid_network_config() -> Result<()> {
+		let tmp_dir = tempfile::tempdir()?;
+		let dir = tmp_dir.path().join("scafalra");
+		fs::create_dir_all(&dir)?;
+		fs::write(dir.join(Config::FILE_NAME), r#"{"proxy": "ftp://foo"}"#)?;
+
+		// Only the commands that access the network fail
+		let scafalra = Scafalra::new(Dirs::single(dir), None, None)?;
+		scafalra.list(ListArgs {
+			table: false,
+		})?;
+
+		let err = scafalra.rate_limit().unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<ScafalraError>(),
+			Some(ScafalraError::Usage(_))
+		));
+
+		fs::write(
+			tmp_dir.path().join_slash("scafalra/config.json"),
+			r#"{"ca_bundles": ["missing.pem"]}"#,
+		)?;
+
+		let scafalra = Scafalra::new(
+			Dirs::single(tmp_dir.path().join("scafalra")),
+			None,
+			None,
+		)?;
+		scafalra.list(ListArgs {
+			table: false,
+		})?;
+
+		let err = scafalra.rate_limit().unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<ScafalraError>(),
+			Some(ScafalraError::Filesystem(_))
+		));
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_add_basic() -> Result<()> {
+		let ServerMock {
+			server,
+			download_mock,
+		} = ServerMock::new();
+
+		let ScafalraMock {
+			tmp_dir: _tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().endpoint(&server.url());
+
+		let args = AddArgsMock::new().build();
+		scafalra.add(args.clone())?;
+
+		download_mock.assert();
+
+		let bar_dir = scafalra.cache_dir.join_slash("foo/bar");
+		let actual = fs::read_to_string(&scafalra.store()?.path)?;
+		let expect = StoreJsonMock::new()
+			.cache_dir(&scafalra.cache_dir)
+			.commit(FIXTURE_COMMIT)
+			.source(Source::from(&args))
+			.push("bar", &bar_dir)
+			.build();
+
+		assert!(bar_dir.exists());
+		assert_eq!(actual, expect);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_add_specified_name() -> Result<()> {
+		let ServerMock {
+			server,
+			download_mock,
+		} = ServerMock::new();
+
+		let ScafalraMock {
+			tmp_dir: _tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().endpoint(&server.url());
+
+		let args = AddArgsMock::new().name("foo").build();
+		scafalra.add(args.clone())?;
+
+		download_mock.assert();
+
+		let bar_dir = scafalra.cache_dir.join_slash("foo/bar");
+		let actual = fs::read_to_string(&scafalra.store()?.path)?;
+		let expect = StoreJsonMock::new()
+			.cache_dir(&scafalra.cache_dir)
+			.commit(FIXTURE_COMMIT)
+			.source(Source::from(&args))
+			.push("foo", &bar_dir)
+			.build();
+
+		assert!(bar_dir.exists());
+		assert_eq!(actual, expect);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_add_depth_1() -> Result<()> {
+		let ServerMock {
+			server,
+			download_mock,
+		} = ServerMock::new();
+
+		let ScafalraMock {
+			tmp_dir: _tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().endpoint(&server.url());
+
+		let args = AddArgsMock::new().depth("1").build();
+		scafalra.add(args.clone())?;
+
+		download_mock.assert();
+
+		let bar_dir = scafalra.cache_dir.join_slash("foo/bar");
+		let actual = fs::read_to_string(&scafalra.store()?.path)?;
+		let expect = StoreJsonMock::new()
+			.cache_dir(&scafalra.cache_dir)
+			.commit(FIXTURE_COMMIT)
+			.source(Source::from(&args))
+			.push("a", &bar_dir.join("a"))
+			.push("b", &bar_dir.join("b"))
+			.push("c", &bar_dir.join("c"))
+			.push("node_modules", &bar_dir.join("node_modules"))
+			.build();
+
+		assert!(bar_dir.exists());
+		assert_eq!(actual, expect);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_add_subdir() -> Result<()> {
+		let ServerMock {
+			server,
+			download_mock,
+		} = ServerMock::new();
+
+		let ScafalraMock {
+			tmp_dir: _tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().endpoint(&server.url());
+
+		let args = AddArgsMock::new().subdir("/a/a1").build();
+		scafalra.add(args.clone())?;
+
+		download_mock.assert();
+
+		let a1_dir = scafalra.cache_dir.join_slash("foo/bar/a/a1");
+		let actual = fs::read_to_string(&scafalra.store()?.path)?;
+		let expect = StoreJsonMock::new()
+			.cache_dir(&scafalra.cache_dir)
+			.commit(FIXTURE_COMMIT)
+			.source(Source::from(&args))
+			.push("a1", &a1_dir)
+			.build();
+
+		assert!(a1_dir.exists());
+		assert_eq!(actual, expect);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_add_subdir_with_name() -> Result<()> {
+		let ServerMock {
+			server,
+			download_mock,
+		} = ServerMock::new();
+
+		let ScafalraMock {
+			tmp_dir: _tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().endpoint(&server.url());
+
+		let args = AddArgsMock::new().subdir("/a/a1").name("baz").build();
+		scafalra.add(args)?;
+
+		download_mock.assert();
+
+		let store = scafalra.store()?;
+		assert_eq!(
+			store.get("baz").unwrap().path,
+			scafalra.cache_dir.join_slash("foo/bar/a/a1")
+		);
+		assert!(store.get("a1").is_none());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_add_subdir_and_depth_1() -> Result<()> {
+		let ServerMock {
+			server,
+			download_mock,
+		} = ServerMock::new();
+
+		let ScafalraMock {
+			tmp_dir: _tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().endpoint(&server.url());
+
+		let args = AddArgsMock::new().subdir("/a").depth("1").build();
+		scafalra.add(args.clone())?;
+
+		download_mock.assert();
+
+		let a_dir = scafalra.cache_dir.join_slash("foo/bar/a");
+		let a1_dir = a_dir.join("a1");
+		let a2_dir = a_dir.join("a2");
+		let a3_dir = a_dir.join("a3");
+		let actual = fs::read_to_string(&scafalra.store()?.path)?;
+		let expect = StoreJsonMock::new()
+			.cache_dir(&scafalra.cache_dir)
+			.commit(FIXTURE_COMMIT)
+			.source(Source::from(&args))
+			.push("a1", &a1_dir)
+			.push("a2", &a2_dir)
+			.push("a3", &a3_dir)
+			.build();
+
+		assert!(a1_dir.exists());
+		assert!(a2_dir.exists());
+		assert!(a3_dir.exists());
+		assert_eq!(actual, expect);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_add_local_dir() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new();
+
+		let src_dir = tmp_dir.path().join("local-tpl");
+		fs::create_dir_all(&src_dir)?;
+		fs::write(src_dir.join("baz.txt"), "baz")?;
+
+		scafalra.set_offline();
+		scafalra.add(
+			AddArgsMock::new()
+				.repository(&src_dir.to_string_lossy())
+				.build(),
+		)?;
+
+		let template = scafalra.store()?.get("local-tpl").unwrap();
+		let cached_dir = scafalra.cache_dir.join_slash("@local/local-tpl");
+
+		assert_eq!(template.path, cached_dir);
+		assert_eq!(template.commit, None);
+		assert_eq!(fs::read_to_string(cached_dir.join("baz.txt"))?, "baz");
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_add_local_same_name() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new();
+
+		for (dir, content) in [("a", "a"), ("b", "b")] {
+			let src_dir = tmp_dir.path().join_iter([dir, "tpl"]);
+			fs::create_dir_all(&src_dir)?;
+			fs::write(src_dir.join("baz.txt"), content)?;
+
+			scafalra.add(
+				AddArgsMock::new()
+					.repository(&src_dir.to_string_lossy())
+					.name(&format!("tpl-{}", dir))
+					.build(),
+			)?;
+		}
+
+		// Adding the same path again reuses its directory
+		scafalra.add(
+			AddArgsMock::new()
+				.repository(
+					&tmp_dir.path().join_slash("a/tpl").to_string_lossy(),
+				)
+				.name("tpl-a")
+				.build(),
+		)?;
+
+		let store = scafalra.store()?;
+		let tpl_a = &store.get("tpl-a").unwrap().path;
+		let tpl_b = &store.get("tpl-b").unwrap().path;
+
+		assert_eq!(tpl_a, &scafalra.cache_dir.join_slash("@local/tpl"));
+		assert_eq!(tpl_b, &scafalra.cache_dir.join_slash("@local/tpl-1"));
+		assert_eq!(fs::read_to_string(tpl_a.join("baz.txt"))?, "a");
+		assert_eq!(fs::read_to_string(tpl_b.join("baz.txt"))?, "b");
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_add_local_zip() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new();
+
+		let zip_path = tmp_dir.path().join("archive.zip");
+		fs::write(&zip_path, zipball("root", &[("baz.txt", "baz")]))?;
+
+		scafalra.set_offline();
+		scafalra.add(
+			AddArgsMock::new()
+				.repository(&zip_path.to_string_lossy())
+				.name("zipped")
+				.build(),
+		)?;
+
+		let template = scafalra.store()?.get("zipped").unwrap();
+
+		assert_eq!(
+			template.path,
+			scafalra.cache_dir.join_slash("@local/archive")
+		);
+		assert_eq!(fs::read_to_string(template.path.join("baz.txt"))?, "baz");
+		assert!(
+			!scafalra
+				.cache_dir
+				.join(super::Scafalra::TMP_DIR_NAME)
+				.exists()
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_add_offline() {
+		let ScafalraMock {
+			tmp_dir: _tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new();
+
+		scafalra.set_offline();
+		let err = scafalra.add(AddArgsMock::new().build()).unwrap_err();
+
+		assert!(matches!(
+			err.downcast_ref(),
+			Some(ScafalraError::Network(_))
+		));
+	}
+
+	#[test]
+	fn test_scafalra_export() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir: _tmp_dir,
+			scafalra,
+			..
+		} = ScafalraMock::new().with_content();
+
+		let list = scafalra.export_list()?;
+
+		assert_eq!(
+			list.templates,
+			vec![Source {
+				repo: "foo/bar".to_string(),
+				name: Some("bar".to_string()),
+				..Source::default()
+			}]
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_import() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new();
+
+		let src_dir = tmp_dir.path().join("local-tpl");
+		fs::create_dir_all(&src_dir)?;
+		let list = SourceList {
+			templates: vec![
+				Source {
+					repo: src_dir.to_string_lossy().to_string(),
+					tags: vec!["local".to_string()],
+					..Source::default()
+				},
+				Source {
+					repo: "foo/bar".to_string(),
+					..Source::default()
+				},
+			],
+		};
+		let file = tmp_dir.path().join("templates.json");
+		fs::write(&file, serde_json::to_string(&list)?)?;
+
+		scafalra.set_offline();
+		assert!(
+			scafalra
+				.import(ImportArgs {
+					file: file.clone()
+				})
+				.is_err()
+		);
+
+		let template = scafalra.store()?.get("local-tpl").unwrap();
+		assert_eq!(
+			template.source.as_ref().map(|source| &source.tags),
+			Some(&vec!["local".to_string()])
+		);
+		assert!(scafalra.store()?.get("bar").is_none());
+
+		let exported = scafalra.export_list()?.templates;
+		assert_eq!(exported.len(), 1);
+		assert!(exported[0].same_origin(&list.templates[0]));
+
+		// The local directory is skipped the second time
+		let cached_file = template.path.join("baz.txt");
+		fs::write(&cached_file, "")?;
+		assert!(
+			scafalra
+				.import(ImportArgs {
+					file
+				})
+				.is_err()
+		);
+		assert!(cached_file.exists());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_sync() -> Result<()> {
+		let mut server = mockito::Server::new();
+		let download_mock = server
+			.mock("GET", "/repos/foo/bar/zipball/v2")
+			.with_header("content-type", "application/zip")
+			.with_body_from_file("fixtures/scafalra-test.zip")
+			.expect(1)
+			.create();
+
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().endpoint(&server.url()).with_content();
+
+		let src_dir = tmp_dir.path().join("local-tpl");
+		fs::create_dir_all(&src_dir)?;
+		scafalra.add(
+			AddArgsMock::new()
+				.repository(&src_dir.to_string_lossy())
+				.build(),
+		)?;
+
+		let registry = tmp_dir.path().join("scafalra.registry.toml");
+		fs::write(
+			&registry,
+			"[[templates]]\nrepo = \"foo/bar\"\nref = \"v2\"\nname = \"bar\"\n",
+		)?;
+		let args = SyncArgs {
+			registry: registry.to_string_lossy().to_string(),
+			prune: true,
+		};
+
+		scafalra.sync(args.clone())?;
+
+		let bar = scafalra.store()?.get("bar").unwrap();
+		assert_eq!(
+			bar.source.as_ref().and_then(|s| s.repo_ref.as_deref()),
+			Some("v2")
+		);
+		assert_eq!(bar.commit.as_deref(), Some(FIXTURE_COMMIT));
+		assert!(scafalra.store()?.get("local-tpl").is_none());
+
+		let content = fs::read_to_string(&scafalra.store()?.path)?;
+		scafalra.sync(args)?;
+
+		download_mock.assert();
+		assert_eq!(fs::read_to_string(&scafalra.store()?.path)?, content);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_add_from_catalog() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir,
+			scafalra,
+			..
+		} = ScafalraMock::new();
+
+		let src_dir = tmp_dir.path().join("local-tpl");
+		fs::create_dir_all(&src_dir)?;
+		let catalog_file = tmp_dir.path().join("catalog.json");
+		fs::write(
+			&catalog_file,
+			serde_json::json!({
+				"templates": [{
+					"name": "local",
+					"tags": ["web"],
+					"source": { "repo": src_dir },
+				}],
+			})
+			.to_string(),
+		)?;
+		fs::write(
+			scafalra.path.join(Config::FILE_NAME),
+			serde_json::json!({ "catalogs": [catalog_file] }).to_string(),
+		)?;
+
+		let mut scafalra =
+			Scafalra::new(Dirs::single(scafalra.path), None, None)?;
+		scafalra.set_offline();
+
+		let err = scafalra
+			.add(AddArgsMock::new().repository("lcoal").catalog().build())
+			.unwrap_err();
+		assert!(err.to_string().contains("A similar template is `local`"));
+
+		// The cached index is used when the catalog is not available
+		fs::remove_file(&catalog_file)?;
+
+		// Other catalogs are used when one is not available
+		fs::write(
+			scafalra.path.join(Config::FILE_NAME),
+			serde_json::json!({
+				"catalogs": [tmp_dir.path().join("missing.json"), catalog_file],
+			})
+			.to_string(),
+		)?;
+		let mut scafalra =
+			Scafalra::new(Dirs::single(scafalra.path), None, None)?;
+		scafalra.set_offline();
+
+		scafalra
+			.add(AddArgsMock::new().repository("local").catalog().build())?;
+
+		let template = scafalra.store()?.get("local").unwrap();
+		assert_eq!(
+			template.source.as_ref().map(|source| &source.tags),
+			Some(&vec!["web".to_string()])
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_create_ok() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().with_content();
+
+		let bar_dir = tmp_dir.path().join("bar");
+
+		// Due to chroot restrictions, a directory is specified here to
+		// simulate the current working directory
+		scafalra.create(
+			CreateArgsMock::new()
+				.name("bar")
+				.destination(&bar_dir)
+				.sub_templates(&["dir-1"])
+				.build(),
+		)?;
+
+		assert!(bar_dir.join("baz.txt").exists());
+		assert!(bar_dir.join("dir-1").exists());
+		assert!(!bar_dir.join(SUB_TEMPLATE_DIR).exists());
+		assert!(!tmp_dir.path().join(".bar.scafalra-tmp").exists());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_create_remote() -> Result<()> {
+		let ServerMock {
+			server,
+			download_mock,
+		} = ServerMock::new();
+
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().endpoint(&server.url());
+
+		let dest = tmp_dir.path().join("dest");
+		// Templates of the GitHub owner `r`
+		let owner_dir = scafalra.cache_dir.join_iter(["r", "foo"]);
+		fs::create_dir_all(&owner_dir)?;
+
+		scafalra.create(
+			CreateArgsMock::new()
+				.name("foo/bar/b/b1")
+				.destination(&dest)
+				.build(),
+		)?;
+
+		download_mock.assert();
+
+		assert!(dest.join("b1.txt").exists());
+		assert_eq!(Answers::read(&dest)?.subdir, "b/b1");
+		assert!(scafalra.store()?.get("b1").is_none());
+		assert!(owner_dir.exists());
+		assert_eq!(
+			fs::read_dir(scafalra.cache_dir.join(Scafalra::REMOTE_DIR_NAME))?
+				.count(),
+			0
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_create_remote_save() -> Result<()> {
+		let ServerMock {
+			server,
+			download_mock,
+		} = ServerMock::new();
+
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().endpoint(&server.url());
+
+		let dest = tmp_dir.path().join("dest");
+
+		scafalra.create(
+			CreateArgsMock::new()
+				.name("foo/bar/b/b1")
+				.destination(&dest)
+				.save()
+				.build(),
+		)?;
+
+		download_mock.assert();
+
+		let template = scafalra.store()?.get("b1").unwrap();
+		assert_eq!(
+			template.path,
+			scafalra.cache_dir.join_slash("foo/bar/b/b1")
+		);
+		assert!(dest.join("b1.txt").exists());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_create_remote_save_dry_run() -> Result<()> {
+		let ServerMock {
+			server,
+			download_mock,
+		} = ServerMock::new();
+
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().endpoint(&server.url());
+
+		let dest = tmp_dir.path().join("dest");
+
+		scafalra.create(
+			CreateArgsMock::new()
+				.name("foo/bar/b/b1")
+				.destination(&dest)
+				.save()
+				.dry_run()
+				.build(),
+		)?;
+
+		download_mock.assert();
+
+		assert!(scafalra.store()?.get("b1").is_none());
+		assert!(!scafalra.cache_dir.join("foo").exists());
+		assert!(!dest.exists());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_create_ref_of_template() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().with_content();
+
+		let err = scafalra
+			.create(
+				CreateArgsMock::new()
+					.name("bar")
+					.destination(&tmp_dir.path().join("bar"))
+					.repo_ref("v1")
+					.build(),
+			)
+			.unwrap_err();
+
+		assert!(matches!(
+			err.downcast_ref::<ScafalraError>(),
+			Some(ScafalraError::Usage(_))
+		));
+		assert!(err.to_string().contains("`--ref` can only be used"));
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_create_extends() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().with_content();
+
+		let bar_dir = scafalra.cache_dir.join_slash("foo/bar");
+		let base_dir = scafalra.cache_dir.join_slash("foo/base");
+		fs::create_dir_all(&base_dir)?;
+		fs::write(base_dir.join("LICENSE"), "MIT")?;
+		fs::write(base_dir.join("baz.txt"), "base")?;
+		scafalra
+			.store_mut()?
+			.add(Template::new("base", "url", &base_dir));
+		scafalra.store_mut()?.save()?;
+
+		manifest_setup(&bar_dir, "extends = [\"base\"]");
+
+		let dest = tmp_dir.path().join("bar");
+		scafalra.create(
+			CreateArgsMock::new().name("bar").destination(&dest).build(),
+		)?;
+
+		assert_eq!(fs::read_to_string(dest.join("LICENSE"))?, "MIT");
+		assert_eq!(fs::read_to_string(dest.join("baz.txt"))?, "");
+
+		// missing base
+		manifest_setup(&bar_dir, "extends = [\"bas\"]");
+		let err = scafalra
+			.create(CreateArgsMock::new().name("bar").dry_run().build())
+			.unwrap_err();
+		let Some(ScafalraError::NotFound(msg)) = err.downcast_ref() else {
+			panic!("unexpected error: {:?}", err);
+		};
+		assert!(msg.contains("A similar template is `base`"));
+
+		// cycle
+		manifest_setup(&bar_dir, "extends = [\"base\"]");
+		manifest_setup(&base_dir, "extends = [\"bar\"]");
+		let err = scafalra
+			.create(CreateArgsMock::new().name("bar").dry_run().build())
+			.unwrap_err();
+		assert!(err.to_string().contains("`bar` -> `base` -> `bar`"));
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_create_sub_template_meta() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().with_content();
+
+		let sub_tpls_dir = scafalra.cache_dir.join_slash("foo/bar/.scafalra");
+		fs::write(sub_tpls_dir.join_slash("dir-1/ci.yml"), "")?;
+		fs::write(sub_tpls_dir.join_slash("dir-2/extra.txt"), "")?;
+		manifest_setup(
+			&scafalra.cache_dir.join_slash("foo/bar"),
+			r#"
+[sub_templates.dir-1]
+target = ".github/workflows"
+requires = ["dir-2"]
+
+[sub_templates.dir-2]
+merge = true
+"#,
+		);
+
+		let dest = tmp_dir.path().join("bar");
+		scafalra.create(
+			CreateArgsMock::new()
+				.name("bar")
+				.destination(&dest)
+				.sub_templates(&["dir-1"])
+				.build(),
+		)?;
+
+		assert!(dest.join_slash(".github/workflows/ci.yml").exists());
+		assert!(dest.join("extra.txt").exists());
+		assert!(!dest.join("dir-1").exists());
+		assert_eq!(Answers::read(&dest)?.sub_templates, vec!["dir-2", "dir-1"]);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_create_dry_run() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().with_content();
+
+		fs::write(
+			scafalra
+				.cache_dir
+				.join_slash("foo/bar/.scafalra/dir-1/ci.yml"),
+			"on: push",
+		)?;
+
+		let bar_dir = tmp_dir.path().join("bar");
+
+		scafalra.create(
+			CreateArgsMock::new()
+				.name("bar")
+				.destination(&bar_dir)
+				.sub_templates(&["dir-1"])
+				.dry_run()
+				.build(),
+		)?;
+
+		assert!(!bar_dir.exists());
+
+		// The plan that the dry run printed
+		let template = scafalra.store()?.get("bar").unwrap().clone();
+		let plan = plan_template(
+			&[template.path.as_path()],
+			&template.sub_templates,
+			&["dir-1".to_string()],
+			&BTreeMap::new(),
+		)?;
+
+		assert_eq!(
+			plan.print(),
+			"       0 B  baz.txt\n         -  dir-1/\n       8 B  dir-1/ci.yml\n2 files, 1 directories, 8 B in total"
+		);
+		assert!(plan.print_conflicts(&bar_dir).is_none());
+
+		// An existing file conflicts when merging, and is left untouched
+		fs::create_dir_all(&bar_dir)?;
+		fs::write(bar_dir.join("baz.txt"), "baz")?;
+
+		scafalra.create(
+			CreateArgsMock::new()
+				.name("bar")
+				.destination(&bar_dir)
+				.sub_templates(&["dir-1"])
+				.merge(ConflictPolicy::Overwrite)
+				.dry_run()
+				.build(),
+		)?;
+
+		let conflicts = plan.print_conflicts(&bar_dir).unwrap();
+		assert!(
+			conflicts.contains("`baz.txt` already exists in the destination")
+		);
+		assert_eq!(conflicts.lines().count(), 1);
+		assert_eq!(fs::read_to_string(bar_dir.join("baz.txt"))?, "baz");
+		assert_eq!(fs::read_dir(&bar_dir)?.count(), 1);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_create_merge() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().with_content();
+
+		let bar_dir = tmp_dir.path().join("bar");
+		fs::create_dir_all(&bar_dir)?;
+		fs::write(bar_dir.join("baz.txt"), "baz")?;
+		fs::write(bar_dir.join("qux.txt"), "qux")?;
+		Answers {
+			template: "foo".to_string(),
+			..Answers::default()
+		}
+		.write(&bar_dir)?;
+
+		let args = CreateArgsMock::new()
+			.name("bar")
+			.destination(&bar_dir)
+			.sub_templates(&["dir-1"])
+			.build();
+
+		assert!(scafalra.create(args.clone()).is_err());
+
+		scafalra.create(
+			CreateArgsMock::new()
+				.name("bar")
+				.destination(&bar_dir)
+				.sub_templates(&["dir-1"])
+				.merge(ConflictPolicy::KeepBoth)
+				.build(),
+		)?;
+
+		assert_eq!(fs::read_to_string(bar_dir.join("baz.txt"))?, "baz");
+		assert!(bar_dir.join("baz-scafalra.txt").exists());
+		assert!(bar_dir.join("qux.txt").exists());
+		assert!(bar_dir.join("dir-1").exists());
+		assert_eq!(Answers::read(&bar_dir)?.template, "foo");
+
+		let baz_dir = tmp_dir.path().join("baz");
+		fs::create_dir_all(&baz_dir)?;
+
+		scafalra.create(
+			CreateArgsMock::new()
+				.name("bar")
+				.destination(&baz_dir)
+				.merge(ConflictPolicy::Skip)
+				.build(),
+		)?;
+
+		assert_eq!(Answers::read(&baz_dir)?.template, "bar");
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_create_interrupted() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().with_content();
+
+		let dest = tmp_dir.path().join_slash("a/b/bar");
+
+		// While the plan is executed in the staging directory
+		interrupt_at(1);
+		let err = scafalra
+			.create(
+				CreateArgsMock::new().name("bar").destination(&dest).build(),
+			)
+			.unwrap_err();
+
+		assert_eq!(exit_code(&err), 130);
+		assert!(!tmp_dir.path().join("a").exists());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_create_merge_failed() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().with_content();
+
+		let bar_dir = scafalra.cache_dir.join_slash("foo/bar");
+		fs::write(bar_dir.join("baz.txt"), "new")?;
+		fs::write(bar_dir.join_slash(".scafalra/dir-1/ci.yml"), "")?;
+		manifest_setup(
+			&bar_dir,
+			r#"
+[sub_templates.dir-1]
+target = "x/y"
+"#,
+		);
+
+		// `x` can not be created in the destination, after `baz.txt` has been
+		// overwritten
+		let dest = tmp_dir.path().join("bar");
+		fs::create_dir_all(&dest)?;
+		fs::write(dest.join("baz.txt"), "old")?;
+		fs::write(dest.join("x"), "x")?;
+
+		let ret = scafalra.create(
+			CreateArgsMock::new()
+				.name("bar")
+				.destination(&dest)
+				.sub_templates(&["dir-1"])
+				.merge(ConflictPolicy::Overwrite)
+				.build(),
+		);
+
+		assert!(ret.is_err());
+		assert_eq!(fs::read_to_string(dest.join("baz.txt"))?, "old");
+		assert_eq!(fs::read_to_string(dest.join("x"))?, "x");
+		assert!(!dest.join(Answers::FILE_NAME).exists());
+		assert_eq!(fs::read_dir(&dest)?.count(), 2);
+		assert_eq!(fs::read_dir(tmp_dir.path())?.count(), 2);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_create_bad_args() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir: _tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new();
+
+		let ret = scafalra.create(CreateArgsMock::new().build());
+
+		assert!(matches!(
+			ret.unwrap_err().downcast_ref::<ScafalraError>(),
+			Some(ScafalraError::Usage(_))
+		));
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_scafalra_create_not_found() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir: _tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new();
+
+		let ret = scafalra.create(CreateArgsMock::new().name("bar").build());
+
+		assert!(matches!(
+			ret.unwrap_err().downcast_ref::<ScafalraError>(),
+			Some(ScafalraError::NotFound(_))
+		));
+
+		Ok(())
 	}
 
-	impl ServerMock {
-		pub fn new() -> Self {
-			let mut server = mockito::Server::new();
+	#[test]
+	fn test_scafalra_create_unknown_sub_template() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().with_content();
 
-			let download_mock = server
-				.mock("GET", "/repos/foo/bar/zipball")
-				.with_status(200)
-				.with_header("content-type", "application/zip")
-				.with_body_from_file("fixtures/scafalra-test.zip")
-				.create();
+		let bar_dir = tmp_dir.path().join("bar");
 
-			Self {
-				server,
-				download_mock,
-			}
-		}
+		let ret = scafalra.create(
+			CreateArgsMock::new()
+				.name("bar")
+				.destination(&bar_dir)
+				.sub_templates(&["dir-1", "dri-2"])
+				.build(),
+		);
+
+		let err = ret.unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<ScafalraError>(),
+			Some(ScafalraError::NotFound(_))
+		));
+		assert!(
+			err.to_string()
+				.contains("A similar sub template is `dir-2`")
+		);
+		assert!(!bar_dir.exists());
+
+		Ok(())
 	}
-}
 
-#[cfg(test)]
-mod tests {
-	use std::fs;
+	#[test]
+	fn test_scafalra_create_execute_failed() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir,
+			mut scafalra,
+			..
+		} = ScafalraMock::new().with_content();
 
-	use anyhow::Result;
-	use similar_asserts::assert_eq;
+		// The file `x` of `dir-2` replaces the directory `x` of the template,
+		// so `x/f` can not be written
+		let bar_dir = scafalra.cache_dir.join_slash("foo/bar");
+		fs::create_dir_all(bar_dir.join("x"))?;
+		fs::write(bar_dir.join_slash("x/f"), "")?;
+		fs::write(bar_dir.join_slash(".scafalra/dir-2/x"), "")?;
+		manifest_setup(
+			&bar_dir,
+			r#"
+[sub_templates.dir-2]
+merge = true
+"#,
+		);
 
-	use super::test_utils::{ScafalraMock, ServerMock};
-	use crate::{
-		cli::{test_utils::AddArgsMock, CreateArgs, RemoveArgs, RenameArgs},
-		path_ext::*,
-		store::test_utils::StoreJsonMock,
-		sub_template::SUB_TEMPLATE_DIR,
-	};
+		let dest = tmp_dir.path().join("bar");
+		let ret = scafalra.create(
+			CreateArgsMock::new()
+				.name("bar")
+				.destination(&dest)
+				.sub_templates(&["dir-2"])
+				.build(),
+		);
+
+		// The partially created destination is not left behind
+		assert!(ret.is_err());
+		assert!(!dest.exists());
+
+		Ok(())
+	}
 
 	#[test]
-	fn test_scafalra_new() {
+	fn test_scafalra_remove_bad_args() -> Result<()> {
 		let ScafalraMock {
 			tmp_dir: _tmp_dir,
-			scafalra,
+			mut scafalra,
 			..
 		} = ScafalraMock::new();
 
-		assert!(scafalra.cache_dir.exists());
-		assert!(scafalra.store.path.exists());
-		assert!(scafalra.config.path.exists());
+		let ret = scafalra.remove(RemoveArgs {
+			names: None,
+		});
+
+		assert!(ret.is_err());
+
+		Ok(())
 	}
 
 	#[test]
-	fn test_scafalra_add_basic() -> Result<()> {
-		let ServerMock {
-			server,
-			download_mock,
-		} = ServerMock::new();
-
+	fn test_scafalra_remove_not_found() -> Result<()> {
 		let ScafalraMock {
 			tmp_dir: _tmp_dir,
 			mut scafalra,
 			..
-		} = ScafalraMock::new().endpoint(&server.url());
-
-		scafalra.add(AddArgsMock::new().build())?;
-
-		download_mock.assert();
+		} = ScafalraMock::new().with_content();
 
-		let bar_dir = scafalra.cache_dir.join_slash("foo/bar");
-		let actual = fs::read_to_string(&scafalra.store.path)?;
-		let expect = StoreJsonMock::new().push("bar", &bar_dir).build();
+		let err = scafalra
+			.remove(RemoveArgs {
+				names: Some(vec!["bar".to_string(), "baz".to_string()]),
+			})
+			.unwrap_err();
 
-		assert!(bar_dir.exists());
-		assert_eq!(actual, expect);
+		assert_eq!(exit_code(&err), 3);
+		assert!(scafalra.store()?.get("bar").is_some());
 
 		Ok(())
 	}
 
 	#[test]
-	fn test_scafalra_add_specified_name() -> Result<()> {
-		let ServerMock {
-			server,
-			download_mock,
-		} = ServerMock::new();
-
+	fn test_scafalra_rename_bad_args() -> Result<()> {
 		let ScafalraMock {
 			tmp_dir: _tmp_dir,
 			mut scafalra,
 			..
-		} = ScafalraMock::new().endpoint(&server.url());
+		} = ScafalraMock::new();
 
-		scafalra.add(AddArgsMock::new().name("foo").build())?;
+		let ret = scafalra.rename(RenameArgs {
+			name: None,
+			new_name: None,
+		});
 
-		download_mock.assert();
+		assert!(ret.is_err());
 
-		let bar_dir = scafalra.cache_dir.join_slash("foo/bar");
-		let actual = fs::read_to_string(&scafalra.store.path)?;
-		let expect = StoreJsonMock::new().push("foo", &bar_dir).build();
+		Ok(())
+	}
 
-		assert!(bar_dir.exists());
-		assert_eq!(actual, expect);
+	#[test]
+	fn test_scafalra_show_tree() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir: _tmp_dir,
+			scafalra,
+			..
+		} = ScafalraMock::new().with_content();
+
+		let template = scafalra.store()?.get("bar").unwrap();
+
+		assert_eq!(
+			render_show(template, None)?,
+			concat!(
+				"bar\n",
+				"├── .scafalra/\n",
+				"│   ├── dir-1/\n",
+				"│   └── dir-2/\n",
+				"└── baz.txt",
+			)
+		);
 
 		Ok(())
 	}
 
 	#[test]
-	fn test_scafalra_add_depth_1() -> Result<()> {
-		let ServerMock {
-			server,
-			download_mock,
-		} = ServerMock::new();
-
+	fn test_scafalra_show_file() -> Result<()> {
 		let ScafalraMock {
 			tmp_dir: _tmp_dir,
-			mut scafalra,
+			scafalra,
 			..
-		} = ScafalraMock::new().endpoint(&server.url());
+		} = ScafalraMock::new().with_content();
 
-		scafalra.add(AddArgsMock::new().depth("1").build())?;
+		let template = scafalra.store()?.get("bar").unwrap();
+		fs::write(template.path.join("baz.txt"), "baz\n")?;
 
-		download_mock.assert();
+		assert_eq!(render_show(template, Some(Path::new("baz.txt")))?, "baz");
 
-		let bar_dir = scafalra.cache_dir.join_slash("foo/bar");
-		let actual = fs::read_to_string(&scafalra.store.path)?;
-		let expect = StoreJsonMock::new()
-			.push("a", &bar_dir.join("a"))
-			.push("b", &bar_dir.join("b"))
-			.push("c", &bar_dir.join("c"))
-			.push("node_modules", &bar_dir.join("node_modules"))
-			.build();
+		Ok(())
+	}
 
-		assert!(bar_dir.exists());
-		assert_eq!(actual, expect);
+	#[test]
+	fn test_scafalra_show_outside() -> Result<()> {
+		let ScafalraMock {
+			tmp_dir,
+			scafalra,
+			..
+		} = ScafalraMock::new().with_content();
+
+		let template = scafalra.store()?.get("bar").unwrap();
+
+		for path in ["../..", "/etc"] {
+			let err = render_show(template, Some(Path::new(path))).unwrap_err();
+			assert!(matches!(
+				err.downcast_ref::<ScafalraError>(),
+				Some(ScafalraError::Usage(_))
+			));
+		}
+
+		#[cfg(unix)]
+		{
+			std::os::unix::fs::symlink(
+				tmp_dir.path(),
+				template.path.join("up"),
+			)?;
+			assert!(render_show(template, Some(Path::new("up"))).is_err());
+		}
 
 		Ok(())
 	}
 
 	#[test]
-	fn test_scafalra_add_subdir() -> Result<()> {
-		let ServerMock {
-			server,
-			download_mock,
-		} = ServerMock::new();
+	fn test_scafalra_show_not_found() {
+		let ScafalraMock {
+			tmp_dir: _tmp_dir,
+			scafalra,
+			..
+		} = ScafalraMock::new().with_content();
+
+		let template = scafalra.store().unwrap().get("bar").unwrap();
+
+		assert!(render_show(template, Some(Path::new("qux"))).is_err());
+	}
 
+	#[test]
+	fn test_scafalra_broken_store() -> Result<()> {
 		let ScafalraMock {
 			tmp_dir: _tmp_dir,
 			mut scafalra,
 			..
-		} = ScafalraMock::new().endpoint(&server.url());
+		} = ScafalraMock::new().with_content();
 
-		scafalra.add(AddArgsMock::new().subdir("/a/a1").build())?;
+		fs::write(scafalra.path.join(Store::FILE_NAME), "{")?;
 
-		download_mock.assert();
+		scafalra.token(TokenArgs {
+			token: Some("token2".to_string()),
+		})?;
 
-		let a1_dir = scafalra.cache_dir.join_slash("foo/bar/a/a1");
-		let actual = fs::read_to_string(&scafalra.store.path)?;
-		let expect = StoreJsonMock::new().push("a1", &a1_dir).build();
+		let err = scafalra
+			.list(ListArgs {
+				table: false,
+			})
+			.unwrap_err();
 
-		assert!(a1_dir.exists());
-		assert_eq!(actual, expect);
+		assert!(err.to_string().contains("sca doctor"));
 
 		Ok(())
 	}
 
 	#[test]
-	fn test_scafalra_add_subdir_and_depth_1() -> Result<()> {
-		let ServerMock {
-			server,
-			download_mock,
-		} = ServerMock::new();
-
+	fn test_scafalra_doctor_restore_backup() -> Result<()> {
 		let ScafalraMock {
 			tmp_dir: _tmp_dir,
 			mut scafalra,
 			..
-		} = ScafalraMock::new().endpoint(&server.url());
+		} = ScafalraMock::new().with_content();
 
-		scafalra.add(AddArgsMock::new().subdir("/a").depth("1").build())?;
+		scafalra.store_mut()?.save()?;
+		fs::write(scafalra.path.join(Store::FILE_NAME), "{")?;
 
-		download_mock.assert();
+		scafalra.doctor(DoctorArgs {
+			rebuild: false,
+		})?;
 
-		let a_dir = scafalra.cache_dir.join_slash("foo/bar/a");
-		let a1_dir = a_dir.join("a1");
-		let a2_dir = a_dir.join("a2");
-		let a3_dir = a_dir.join("a3");
-		let actual = fs::read_to_string(&scafalra.store.path)?;
-		let expect = StoreJsonMock::new()
-			.push("a1", &a1_dir)
-			.push("a2", &a2_dir)
-			.push("a3", &a3_dir)
-			.build();
+		let store = Store::new(&scafalra.path, &scafalra.cache_dir)?;
 
-		assert!(a1_dir.exists());
-		assert!(a2_dir.exists());
-		assert!(a3_dir.exists());
-		assert_eq!(actual, expect);
+		assert!(store.get("bar").is_some());
+		assert_eq!(
+			fs::read_to_string(scafalra.path.join("store.json.broken"))?,
+			"{"
+		);
 
 		Ok(())
 	}
 
 	#[test]
-	fn test_scafalra_create_ok() -> Result<()> {
+	fn test_scafalra_doctor_rebuild() -> Result<()> {
 		let ScafalraMock {
-			tmp_dir,
-			scafalra,
+			tmp_dir: _tmp_dir,
+			mut scafalra,
 			..
 		} = ScafalraMock::new().with_content();
 
-		let bar_dir = tmp_dir.path().join("bar");
+		fs::write(scafalra.path.join(Store::FILE_NAME), "{")?;
 
-		scafalra.create(CreateArgs {
-			name: Some("bar".to_string()),
-			// Due to chroot restrictions, a directory is specified here to
-			// simulate the current working directory
-			destination: Some(bar_dir.clone()),
-			sub_templates: Some(vec!["dir-1".to_string()]),
+		scafalra.doctor(DoctorArgs {
+			rebuild: false,
 		})?;
 
-		assert!(bar_dir.join("baz.txt").exists());
-		assert!(bar_dir.join("dir-1").exists());
-		assert!(!bar_dir.join(SUB_TEMPLATE_DIR).exists());
+		let store = Store::new(&scafalra.path, &scafalra.cache_dir)?;
+		let template = store.get("bar").unwrap();
+
+		assert_eq!(template.path, scafalra.cache_dir.join_slash("foo/bar"));
 
 		Ok(())
 	}
 
 	#[test]
-	fn test_scafalra_create_bad_args() -> Result<()> {
+	fn test_scafalra_doctor_force_rebuild() -> Result<()> {
 		let ScafalraMock {
 			tmp_dir: _tmp_dir,
-			scafalra,
+			mut scafalra,
 			..
-		} = ScafalraMock::new();
+		} = ScafalraMock::new().with_content();
 
-		let ret = scafalra.create(CreateArgs {
-			name: None,
-			destination: None,
-			sub_templates: None,
-		});
+		// Both `store.json` and its backup only know `baz`
+		scafalra.store_mut()?.rename("bar", "baz")?;
+		scafalra.store_mut()?.save()?;
+		fs::create_dir_all(scafalra.cache_dir.join_slash("foo/qux"))?;
 
-		assert!(ret.is_err());
+		scafalra.doctor(DoctorArgs {
+			rebuild: true,
+		})?;
+
+		let store = Store::new(&scafalra.path, &scafalra.cache_dir)?;
+
+		assert_eq!(
+			store.get("bar").unwrap().path,
+			scafalra.cache_dir.join_slash("foo/bar")
+		);
+		assert!(store.get("qux").is_some());
+		assert!(store.get("baz").is_none());
+		assert!(scafalra.path.join("store.json.broken").exists());
 
 		Ok(())
 	}
 
 	#[test]
-	fn test_scafalra_create_not_found() -> Result<()> {
+	fn test_scafalra_doctor_prune_missing() -> Result<()> {
 		let ScafalraMock {
 			tmp_dir: _tmp_dir,
-			scafalra,
+			mut scafalra,
 			..
-		} = ScafalraMock::new();
+		} = ScafalraMock::new().with_content();
 
-		let ret = scafalra.create(CreateArgs {
-			name: Some("bar".to_string()),
-			destination: None,
-			sub_templates: None,
-		});
+		fs::remove_dir_all(scafalra.cache_dir.join_slash("foo/bar"))?;
 
-		assert!(ret.is_err());
+		scafalra.doctor(DoctorArgs {
+			rebuild: false,
+		})?;
+
+		assert!(
+			Store::new(&scafalra.path, &scafalra.cache_dir)?
+				.get("bar")
+				.is_none()
+		);
 
 		Ok(())
 	}
 
 	#[test]
-	fn test_scafalra_remove_bad_args() -> Result<()> {
+	fn test_scafalra_upgrade() -> Result<()> {
+		let mut server = mockito::Server::new();
+
+		let v1_mock = server
+			.mock("GET", "/repos/foo/bar/zipball/111")
+			.with_body(zipball(
+				"foo-bar-111",
+				&[("a.txt", "a\n"), ("b.txt", "b\n")],
+			))
+			.expect(2)
+			.create();
+		let v2_mock = server
+			.mock("GET", "/repos/foo/bar/zipball")
+			.with_body(zipball(
+				"foo-bar-222",
+				&[("a.txt", "A\n"), ("b.txt", "B\n"), ("c.txt", "c\n")],
+			))
+			.create();
+
 		let ScafalraMock {
-			tmp_dir: _tmp_dir,
+			tmp_dir,
 			mut scafalra,
 			..
-		} = ScafalraMock::new();
+		} = ScafalraMock::new().endpoint(&server.url());
 
-		let ret = scafalra.remove(RemoveArgs {
-			names: None,
-		});
+		let proj_dir = tmp_dir.path().join("proj");
 
-		assert!(ret.is_err());
+		scafalra.add(AddArgsMock::new().commit("111").build())?;
+		scafalra.create(
+			CreateArgsMock::new()
+				.name("bar")
+				.destination(&proj_dir)
+				.build(),
+		)?;
+
+		assert_eq!(Answers::read(&proj_dir)?.commit.as_deref(), Some("111"));
+
+		fs::write(proj_dir.join("b.txt"), "b2\n")?;
+
+		scafalra.add(AddArgsMock::new().build())?;
+		scafalra.upgrade(UpgradeArgs {
+			directory: Some(proj_dir.clone()),
+		})?;
+
+		v1_mock.assert();
+		v2_mock.assert();
+
+		assert_eq!(fs::read_to_string(proj_dir.join("a.txt"))?, "A\n");
+		assert_eq!(fs::read_to_string(proj_dir.join("c.txt"))?, "c\n");
+		assert!(
+			fs::read_to_string(proj_dir.join("b.txt"))?.contains("<<<<<<<")
+		);
+		assert_eq!(Answers::read(&proj_dir)?.commit.as_deref(), Some("222"));
 
 		Ok(())
 	}
 
 	#[test]
-	fn test_scafalra_rename_bad_args() -> Result<()> {
+	fn test_scafalra_upgrade_extends() -> Result<()> {
+		let mut server = mockito::Server::new();
+
+		let v1_mock = server
+			.mock("GET", "/repos/foo/bar/zipball/111")
+			.with_body(zipball("foo-bar-111", &[("a.txt", "a\n")]))
+			.expect(2)
+			.create();
+		let v2_mock = server
+			.mock("GET", "/repos/foo/bar/zipball")
+			.with_body(zipball(
+				"foo-bar-222",
+				&[
+					("a.txt", "a\n"),
+					(".scafalra/manifest.toml", "extends = [\"ci\"]"),
+				],
+			))
+			.create();
+
 		let ScafalraMock {
-			tmp_dir: _tmp_dir,
+			tmp_dir,
 			mut scafalra,
 			..
-		} = ScafalraMock::new();
+		} = ScafalraMock::new().endpoint(&server.url());
 
-		let ret = scafalra.rename(RenameArgs {
-			name: None,
-			new_name: None,
-		});
+		let ci_dir = tmp_dir.path().join("ci");
+		fs::create_dir_all(&ci_dir)?;
+		fs::write(ci_dir.join("ci.yml"), "ci\n")?;
+		scafalra.add(
+			AddArgsMock::new()
+				.repository(&ci_dir.to_string_lossy())
+				.build(),
+		)?;
 
-		assert!(ret.is_err());
+		let proj_dir = tmp_dir.path().join("proj");
+
+		scafalra.add(AddArgsMock::new().commit("111").build())?;
+		scafalra.create(
+			CreateArgsMock::new()
+				.name("bar")
+				.destination(&proj_dir)
+				.build(),
+		)?;
+
+		assert!(!proj_dir.join("ci.yml").exists());
+
+		scafalra.add(AddArgsMock::new().build())?;
+		scafalra.upgrade(UpgradeArgs {
+			directory: Some(proj_dir.clone()),
+		})?;
+
+		v1_mock.assert();
+		v2_mock.assert();
+
+		assert_eq!(fs::read_to_string(proj_dir.join("ci.yml"))?, "ci\n");
 
 		Ok(())
 	}
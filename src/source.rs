@@ -0,0 +1,178 @@
+use std::{fs, path::Path};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{cli::AddArgs, error::ScafalraError};
+
+/// How a template was added, so that it can be added again on another machine
+#[derive(Deserialize, Serialize, Clone, Default, Debug, PartialEq)]
+pub struct Source {
+	/// `owner/name`, a GitHub url, a local directory or a zip archive
+	pub repo: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub subdir: Option<String>,
+	#[serde(default, rename = "ref", skip_serializing_if = "Option::is_none")]
+	pub repo_ref: Option<String>,
+	#[serde(default, skip_serializing_if = "is_zero")]
+	pub depth: u8,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub name: Option<String>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub tags: Vec<String>,
+}
+
+fn is_zero(depth: &u8) -> bool {
+	*depth == 0
+}
+
+impl From<&AddArgs> for Source {
+	fn from(args: &AddArgs) -> Self {
+		Self {
+			repo: args.repository.clone(),
+			subdir: args.subdir.clone(),
+			repo_ref: args.repo_ref().map(String::from),
+			depth: args.depth.parse().unwrap_or_default(),
+			name: args.name.clone(),
+			tags: args.tags.clone(),
+		}
+	}
+}
+
+impl Source {
+	/// Whether both add the same templates, regardless of name and tags
+	pub fn same_origin(&self, other: &Self) -> bool {
+		self.repo == other.repo
+			&& self.subdir == other.subdir
+			&& self.repo_ref == other.repo_ref
+			&& self.depth == other.depth
+	}
+}
+
+impl std::fmt::Display for Source {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.repo)?;
+
+		if let Some(subdir) = &self.subdir {
+			write!(f, " --subdir {}", subdir)?;
+		}
+
+		if let Some(repo_ref) = &self.repo_ref {
+			write!(f, " --ref {}", repo_ref)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// The file written by `sca export`, a registry file for `sca sync` is the
+/// same list in TOML
+#[derive(Deserialize, Serialize, Default)]
+pub struct SourceList {
+	#[serde(default)]
+	pub templates: Vec<Source>,
+}
+
+impl SourceList {
+	pub fn read(path: &Path) -> Result<Self> {
+		let content = read_file(path)?;
+
+		Self::from_json(&content, &path.to_string_lossy())
+	}
+
+	/// `origin` is the file or url the content is read from
+	pub fn from_json(content: &str, origin: &str) -> Result<Self> {
+		let list = serde_json::from_str(content).map_err(|err| {
+			ScafalraError::Usage(format!(
+				"Failed to parse `{}`: {}",
+				origin, err
+			))
+		})?;
+
+		Ok(list)
+	}
+
+	pub fn from_toml(content: &str, origin: &str) -> Result<Self> {
+		let list = toml::from_str(content).map_err(|err| {
+			ScafalraError::Usage(format!(
+				"Failed to parse `{}`: {}",
+				origin, err
+			))
+		})?;
+
+		Ok(list)
+	}
+}
+
+pub fn read_file(path: &Path) -> Result<String> {
+	let content = fs::read_to_string(path).map_err(|err| {
+		ScafalraError::NotFound(format!(
+			"Failed to read `{}`: {}",
+			path.to_string_lossy(),
+			err
+		))
+	})?;
+
+	Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Source, SourceList};
+	use crate::cli::test_utils::AddArgsMock;
+
+	#[test]
+	fn test_source_from_add_args() {
+		let source =
+			Source::from(&AddArgsMock::new().subdir("a").commit("abc").build());
+
+		assert_eq!(source.repo, "foo/bar");
+		assert_eq!(source.subdir.as_deref(), Some("a"));
+		assert_eq!(source.repo_ref.as_deref(), Some("abc"));
+		assert_eq!(source.depth, 0);
+	}
+
+	#[test]
+	fn test_source_list_serde() {
+		let list = SourceList {
+			templates: vec![Source {
+				repo: "foo/bar".to_string(),
+				depth: 1,
+				..Source::default()
+			}],
+		};
+
+		let json = serde_json::to_string(&list).unwrap();
+		assert_eq!(json, r#"{"templates":[{"repo":"foo/bar","depth":1}]}"#);
+
+		let list: SourceList = serde_json::from_str(&json).unwrap();
+		assert_eq!(list.templates[0].depth, 1);
+	}
+
+	#[test]
+	fn test_source_list_from_toml() {
+		let list = SourceList::from_toml(
+			r#"
+[[templates]]
+repo = "foo/bar"
+ref = "v1"
+
+[[templates]]
+repo = "foo/baz"
+subdir = "/a"
+depth = 1
+tags = ["web"]
+"#,
+			"registry.toml",
+		)
+		.unwrap();
+
+		assert_eq!(list.templates.len(), 2);
+		assert_eq!(list.templates[0].repo_ref.as_deref(), Some("v1"));
+		assert_eq!(list.templates[1].tags, vec!["web"]);
+
+		assert!(
+			SourceList::from_toml("templates = 1", "registry.toml").is_err()
+		);
+	}
+}
@@ -0,0 +1,405 @@
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	process,
+	sync::{
+		atomic::{AtomicBool, AtomicUsize, Ordering},
+		Once,
+	},
+};
+
+use anyhow::Result;
+use remove_dir_all::remove_dir_all;
+
+use crate::{debug, error::ScafalraError};
+
+/// The number of staging directories that exist, Ctrl-C exits immediately if
+/// there is none
+static STAGING: AtomicUsize = AtomicUsize::new(0);
+
+/// Set by Ctrl-C while a staging directory exists, the writes stop at the next
+/// `check_interrupted` and are undone on the main thread
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Fail if Ctrl-C was pressed, called between the writes of `create`
+pub fn check_interrupted() -> Result<()> {
+	#[cfg(test)]
+	let simulated = test_utils::is_simulated();
+	#[cfg(not(test))]
+	let simulated = false;
+
+	if simulated || INTERRUPTED.load(Ordering::SeqCst) {
+		anyhow::bail!(ScafalraError::Interrupted(
+			"Interrupted, nothing was written".to_string()
+		));
+	}
+
+	Ok(())
+}
+
+/// A directory next to the destination that `create` writes to, it is moved
+/// to the destination on `commit` and removed otherwise
+pub struct Staging {
+	pub path: PathBuf,
+	dest: PathBuf,
+	/// The missing parent directories of the destination that were created
+	/// for it, the deepest first
+	created_parents: Vec<PathBuf>,
+	committed: bool,
+}
+
+impl Staging {
+	pub fn new(dest: &Path) -> Result<Self> {
+		let file_name = dest
+			.file_name()
+			.map(|name| name.to_string_lossy().to_string())
+			.unwrap_or_default();
+		let path = dest
+			.parent()
+			.unwrap_or(dest)
+			.join(format!(".{}.scafalra-tmp", file_name));
+
+		debug!("staging: {:?}", path);
+
+		// Left by a process that was killed
+		if path.exists() {
+			remove_dir_all(&path)?;
+		}
+
+		let created_parents = path
+			.parent()
+			.map(|parent| {
+				parent
+					.ancestors()
+					.take_while(|dir| {
+						!dir.as_os_str().is_empty() && !dir.exists()
+					})
+					.map(Path::to_path_buf)
+					.collect()
+			})
+			.unwrap_or_default();
+
+		set_ctrlc_handler();
+		STAGING.fetch_add(1, Ordering::SeqCst);
+
+		let staging = Self {
+			path,
+			dest: dest.to_path_buf(),
+			created_parents,
+			committed: false,
+		};
+
+		fs::create_dir_all(&staging.path)?;
+
+		Ok(staging)
+	}
+
+	/// Rename the staging directory to the destination, which must not exist
+	pub fn commit(mut self) -> Result<()> {
+		fs::rename(&self.path, &self.dest)?;
+		self.committed = true;
+
+		Ok(())
+	}
+
+	/// Move the staged files into the destination, which already exists. The
+	/// files it overwrites are backed up, if any file can not be moved, the
+	/// destination is restored
+	pub fn commit_merge(mut self) -> Result<()> {
+		let mut merge = Merge {
+			backup: self.path.with_extension("scafalra-bak"),
+			moved: Vec::new(),
+			created: Vec::new(),
+		};
+
+		if merge.backup.exists() {
+			remove_dir_all(&merge.backup)?;
+		}
+
+		if let Err(err) =
+			merge.move_files(&self.path, &self.dest, Path::new(""))
+		{
+			if let Err(restore_err) = merge.rollback() {
+				debug!("failed to restore the destination: {:?}", restore_err);
+				// Keep everything that is needed to finish it by hand
+				self.committed = true;
+				anyhow::bail!(
+					"{}\nFailed to restore `{}`, the files not moved yet are kept in `{}` and the overwritten files in `{}`",
+					err,
+					self.dest.to_string_lossy(),
+					self.path.to_string_lossy(),
+					merge.backup.to_string_lossy()
+				);
+			}
+			return Err(err);
+		}
+
+		if merge.backup.exists() {
+			remove_dir_all(&merge.backup)?;
+		}
+		remove_dir_all(&self.path)?;
+		self.committed = true;
+
+		Ok(())
+	}
+}
+
+impl Drop for Staging {
+	fn drop(&mut self) {
+		if !self.committed {
+			if self.path.exists() {
+				let _ = remove_dir_all(&self.path);
+			}
+
+			for dir in &self.created_parents {
+				let _ = fs::remove_dir(dir);
+			}
+		}
+
+		STAGING.fetch_sub(1, Ordering::SeqCst);
+	}
+}
+
+/// What `commit_merge` has done to the destination so far
+struct Merge {
+	/// Where the overwritten files are moved, relative paths are kept
+	backup: PathBuf,
+	/// The moved files and their backups
+	moved: Vec<(PathBuf, Option<PathBuf>)>,
+	/// The directories created in the destination
+	created: Vec<PathBuf>,
+}
+
+impl Merge {
+	fn move_files(&mut self, from: &Path, to: &Path, rel: &Path) -> Result<()> {
+		if !to.is_dir() {
+			fs::create_dir(to)?;
+			self.created.push(to.to_path_buf());
+		}
+
+		let mut entries = fs::read_dir(from)?.collect::<Result<Vec<_>, _>>()?;
+		entries.sort_by_key(|entry| entry.file_name());
+
+		for entry in entries {
+			check_interrupted()?;
+
+			let target = to.join(entry.file_name());
+			let rel = rel.join(entry.file_name());
+
+			if entry.file_type()?.is_dir() {
+				self.move_files(&entry.path(), &target, &rel)?;
+				continue;
+			}
+
+			let backup = if target.exists() {
+				let backup = self.backup.join(&rel);
+				if let Some(parent) = backup.parent() {
+					fs::create_dir_all(parent)?;
+				}
+				fs::rename(&target, &backup)?;
+				Some(backup)
+			} else {
+				None
+			};
+
+			let moved = fs::rename(entry.path(), &target);
+			self.moved.push((target, backup));
+			moved?;
+		}
+
+		Ok(())
+	}
+
+	/// Undo the moves in reverse order
+	fn rollback(&self) -> Result<()> {
+		for (target, backup) in self.moved.iter().rev() {
+			if target.is_file() {
+				fs::remove_file(target)?;
+			}
+			if let Some(backup) = backup {
+				fs::rename(backup, target)?;
+			}
+		}
+
+		for dir in self.created.iter().rev() {
+			fs::remove_dir(dir)?;
+		}
+
+		if self.backup.exists() {
+			remove_dir_all(&self.backup)?;
+		}
+
+		Ok(())
+	}
+}
+
+fn set_ctrlc_handler() {
+	static ONCE: Once = Once::new();
+
+	ONCE.call_once(|| {
+		let _ = ctrlc::set_handler(|| {
+			if STAGING.load(Ordering::SeqCst) == 0 {
+				process::exit(130);
+			}
+			INTERRUPTED.store(true, Ordering::SeqCst);
+		});
+	});
+}
+
+#[cfg(test)]
+pub mod test_utils {
+	use std::cell::Cell;
+
+	thread_local! {
+		static INTERRUPT_AT: Cell<Option<usize>> = const { Cell::new(None) };
+	}
+
+	/// Simulate Ctrl-C at the `n`th call of `check_interrupted` on this
+	/// thread, it is not set by the handler so that other tests are not
+	/// affected
+	pub fn interrupt_at(n: usize) {
+		INTERRUPT_AT.set(Some(n));
+	}
+
+	pub(super) fn is_simulated() -> bool {
+		let Some(n) = INTERRUPT_AT.get() else {
+			return false;
+		};
+
+		if n > 1 {
+			INTERRUPT_AT.set(Some(n - 1));
+			return false;
+		}
+
+		INTERRUPT_AT.set(None);
+
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use anyhow::Result;
+	use tempfile::tempdir;
+
+	use super::{test_utils::interrupt_at, Staging};
+	use crate::error::exit_code;
+
+	#[test]
+	fn test_staging_commit() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let dest = tmp_dir.path().join("foo");
+
+		let staging = Staging::new(&dest)?;
+		assert!(tmp_dir.path().join(".foo.scafalra-tmp").exists());
+		fs::write(staging.path.join("a.txt"), "a")?;
+		staging.commit()?;
+
+		assert!(dest.join("a.txt").exists());
+		assert_eq!(fs::read_dir(tmp_dir.path())?.count(), 1);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_staging_commit_merge() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let dest = tmp_dir.path().join("foo");
+		fs::create_dir_all(dest.join("dir"))?;
+		fs::write(dest.join("dir").join("a.txt"), "old")?;
+		fs::write(dest.join("b.txt"), "b")?;
+
+		let staging = Staging::new(&dest)?;
+		fs::create_dir_all(staging.path.join("dir"))?;
+		fs::write(staging.path.join("dir").join("a.txt"), "new")?;
+		fs::write(staging.path.join("c.txt"), "c")?;
+		staging.commit_merge()?;
+
+		assert_eq!(fs::read_to_string(dest.join("dir").join("a.txt"))?, "new");
+		assert!(dest.join("b.txt").exists());
+		assert!(dest.join("c.txt").exists());
+		assert_eq!(fs::read_dir(tmp_dir.path())?.count(), 1);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_staging_commit_merge_failed() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let dest = tmp_dir.path().join("foo");
+		fs::create_dir_all(&dest)?;
+		fs::write(dest.join("a.txt"), "old")?;
+		fs::write(dest.join("c"), "c")?;
+
+		let staging = Staging::new(&dest)?;
+		fs::write(staging.path.join("a.txt"), "new")?;
+		fs::create_dir_all(staging.path.join("b"))?;
+		fs::write(staging.path.join("b").join("b.txt"), "b")?;
+		fs::create_dir_all(staging.path.join("c"))?;
+		fs::write(staging.path.join("c").join("c.txt"), "c")?;
+
+		// `c` is a file in the destination
+		assert!(staging.commit_merge().is_err());
+
+		assert_eq!(fs::read_to_string(dest.join("a.txt"))?, "old");
+		assert!(!dest.join("b").exists());
+		assert!(dest.join("c").is_file());
+		assert_eq!(fs::read_dir(tmp_dir.path())?.count(), 1);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_staging_commit_merge_interrupted() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let dest = tmp_dir.path().join("foo");
+		fs::create_dir_all(&dest)?;
+		fs::write(dest.join("a.txt"), "old")?;
+
+		let staging = Staging::new(&dest)?;
+		fs::write(staging.path.join("a.txt"), "new")?;
+		fs::write(staging.path.join("b.txt"), "b")?;
+
+		// After `a.txt` is moved
+		interrupt_at(2);
+		let err = staging.commit_merge().unwrap_err();
+
+		assert_eq!(exit_code(&err), 130);
+		assert_eq!(fs::read_to_string(dest.join("a.txt"))?, "old");
+		assert!(!dest.join("b.txt").exists());
+		assert_eq!(fs::read_dir(tmp_dir.path())?.count(), 1);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_staging_drop_created_parents() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let dest = tmp_dir.path().join("a").join("b").join("foo");
+
+		let staging = Staging::new(&dest)?;
+		assert!(staging.path.exists());
+		drop(staging);
+
+		assert_eq!(fs::read_dir(tmp_dir.path())?.count(), 0);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_staging_drop() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let dest = tmp_dir.path().join("foo");
+
+		let staging = Staging::new(&dest)?;
+		fs::write(staging.path.join("a.txt"), "a")?;
+		drop(staging);
+
+		assert!(!dest.exists());
+		assert_eq!(fs::read_dir(tmp_dir.path())?.count(), 0);
+
+		Ok(())
+	}
+}
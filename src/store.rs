@@ -1,24 +1,108 @@
 use std::{
 	collections::BTreeMap,
+	fs, mem,
 	ops::{Deref, DerefMut},
 	path::{Path, PathBuf},
 };
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use path_slash::PathExt;
 use remove_dir_all::remove_dir_all;
-use serde::{Deserialize, Serialize};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
 use tabled::{
 	settings::{format::Format, object::Segment, Alignment, Modify, Style},
 	Table,
 };
 use term_grid::{Cell, Direction, Filling, Grid, GridOptions};
 
-use crate::{json::JsonContent, template::Template};
+use crate::{
+	debug,
+	error::ScafalraError,
+	json::JsonContent,
+	path_ext::{is_portable_absolute, portable_components, JoinIter},
+	sub_template::read_sub_templates,
+	template::Template,
+};
 
 #[derive(Deserialize, Serialize, Default)]
 struct TemplateMap(BTreeMap<String, Template>);
 
-impl JsonContent for TemplateMap {}
+impl TemplateMap {
+	/// Paths in `cache_dir` are stored relative to it with `/` separators, so
+	/// that the store keeps working after the directory is moved or synced to
+	/// another platform
+	fn relativize(&mut self, cache_dir: &Path) {
+		for template in self.values_mut() {
+			template.map_paths(|path| {
+				path.strip_prefix(cache_dir).map_or_else(
+					|_| path.to_path_buf(),
+					|path| PathBuf::from(path.to_slash_lossy().into_owned()),
+				)
+			});
+		}
+	}
+
+	/// Absolute paths written on another machine or before the directory was
+	/// moved are made relative to the `cache` directory they were in
+	fn relativize_foreign(&mut self) {
+		for template in self.values_mut() {
+			template.map_paths(|path| {
+				if !is_portable_absolute(path) {
+					return path.to_path_buf();
+				}
+
+				let components = portable_components(path);
+
+				// `<config dir>/cache/<owner>/<name>`, the config directory is
+				// `scafalra`, or `scafalra/config` on Windows
+				let is_cache = |pos: usize| {
+					components[pos] == "cache" && components.len() - pos > 2
+				};
+
+				(1..components.len())
+					.find(|pos| {
+						is_cache(*pos)
+							&& ["scafalra", "config"]
+								.contains(&components[pos - 1].as_str())
+					})
+					.or_else(|| {
+						(0..components.len()).find(|pos| is_cache(*pos))
+					})
+					.map_or_else(
+						|| path.to_path_buf(),
+						|pos| components[pos + 1..].iter().collect(),
+					)
+			});
+		}
+	}
+
+	/// Relative paths written on Windows are separated by `\`
+	/// Read the sub templates of the templates whose directory exists again
+	fn refresh_sub_templates(&mut self, cache_dir: &Path) {
+		self.resolve(cache_dir);
+
+		for template in self.values_mut() {
+			if template.path.is_dir() {
+				template.sub_templates = read_sub_templates(&template.path);
+			}
+		}
+
+		self.relativize(cache_dir);
+	}
+
+	fn resolve(&mut self, cache_dir: &Path) {
+		for template in self.values_mut() {
+			template.map_paths(|path| {
+				if is_portable_absolute(path) {
+					return path.to_path_buf();
+				}
+
+				cache_dir.join_iter(portable_components(path))
+			});
+		}
+	}
+}
 
 impl Deref for TemplateMap {
 	type Target = BTreeMap<String, Template>;
@@ -34,6 +118,135 @@ impl DerefMut for TemplateMap {
 	}
 }
 
+/// The current schema version of `store.json`
+const STORE_VERSION: u64 = 3;
+
+/// The migration at index `n` upgrades a document from version `n` to `n + 1`
+const MIGRATIONS: [fn(Value) -> Value; STORE_VERSION as usize] =
+	[migrate_v0, migrate_v1, migrate_v2];
+
+/// Version 0 is a bare map of templates
+fn migrate_v0(value: Value) -> Value {
+	json!({
+		"version": 1,
+		"templates": value,
+	})
+}
+
+/// Version 2 stores the paths in the cache directory relative to it, they are
+/// converted by `Store::migrate` which knows the cache directory
+fn migrate_v1(mut value: Value) -> Value {
+	value["version"] = json!(2);
+	value
+}
+
+/// Version 3 records the parent of nested sub templates, they are read again
+/// from the cache by `Store::migrate`
+fn migrate_v2(mut value: Value) -> Value {
+	value["version"] = json!(3);
+	value
+}
+
+fn schema_version(value: &Value) -> u64 {
+	value.get("version").and_then(Value::as_u64).unwrap_or(0)
+}
+
+fn migrate(mut value: Value) -> Result<Value, String> {
+	let version = schema_version(&value);
+
+	if version > STORE_VERSION {
+		return Err(newer_version_msg(version));
+	}
+
+	for migration in &MIGRATIONS[version as usize..] {
+		value = migration(value);
+	}
+
+	Ok(value)
+}
+
+fn newer_version_msg(version: u64) -> String {
+	format!(
+		"`{}` was written by a newer version of scafalra (schema version {}, supported {}), please upgrade scafalra",
+		Store::FILE_NAME,
+		version,
+		STORE_VERSION
+	)
+}
+
+/// The document of `store.json`, older versions are migrated when it is
+/// deserialized
+#[derive(Serialize)]
+struct StoreContent {
+	version: u64,
+	templates: TemplateMap,
+}
+
+impl Default for StoreContent {
+	fn default() -> Self {
+		Self {
+			version: STORE_VERSION,
+			templates: TemplateMap::default(),
+		}
+	}
+}
+
+impl<'de> Deserialize<'de> for StoreContent {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		#[derive(Deserialize)]
+		struct Latest {
+			version: u64,
+			templates: TemplateMap,
+		}
+
+		let value = migrate(Value::deserialize(deserializer)?)
+			.map_err(D::Error::custom)?;
+		let Latest {
+			version,
+			templates,
+		} = serde_json::from_value(value).map_err(D::Error::custom)?;
+
+		Ok(Self {
+			version,
+			templates,
+		})
+	}
+}
+
+impl JsonContent for StoreContent {}
+
+/// A modification of the store, replayed on the latest content when saving
+enum Op {
+	Add(Box<Template>),
+	Remove(String),
+	Rename(String, String),
+}
+
+impl Op {
+	fn apply(self, templates: &mut TemplateMap) {
+		match self {
+			Self::Add(template) => {
+				templates.insert(template.name.clone(), *template);
+			}
+			Self::Remove(name) => {
+				templates.remove(&name);
+			}
+			Self::Rename(name, new_name) => {
+				if templates.contains_key(&new_name) {
+					return;
+				}
+
+				if let Some(template) = templates.remove(&name) {
+					templates.insert(new_name, template);
+				}
+			}
+		}
+	}
+}
+
 struct Changes {
 	inner: Vec<String>,
 }
@@ -70,32 +283,176 @@ impl Changes {
 
 pub struct Store {
 	pub path: PathBuf,
+	cache_dir: PathBuf,
 	templates: TemplateMap,
 	changes: Changes,
+	ops: Vec<Op>,
 }
 
 impl Store {
 	pub const FILE_NAME: &'static str = "store.json";
+	pub const BACKUP_FILE_NAME: &'static str = "store.json.bak";
 
-	pub fn new(scafalra_dir: &Path) -> Result<Self> {
+	/// Relative template paths are resolved against `cache_dir`
+	pub fn new(scafalra_dir: &Path, cache_dir: &Path) -> Result<Self> {
 		let path = scafalra_dir.join(Self::FILE_NAME);
-		let templates = TemplateMap::load(&path)?;
+		Self::migrate(&path, cache_dir)?;
+		let mut templates = StoreContent::load(&path)?.templates;
+		templates.resolve(cache_dir);
 		let changes = Changes::new();
 
 		Ok(Self {
 			path,
+			cache_dir: cache_dir.to_path_buf(),
 			templates,
 			changes,
+			ops: Vec::new(),
 		})
 	}
 
-	pub fn save(&self) -> Result<()> {
-		self.templates.save(&self.path)?;
+	/// Other processes may have saved the store since it was loaded, so the
+	/// modifications are replayed on the latest content under a file lock
+	pub fn save(&mut self) -> Result<()> {
+		let ops = mem::take(&mut self.ops);
+
+		let content = StoreContent::update(&self.path, |content| {
+			for op in ops {
+				op.apply(&mut content.templates);
+			}
+			content.templates.relativize(&self.cache_dir);
+		})?;
+
+		// Restored by `sca doctor` if `store.json` gets broken
+		content.save(&self.path.with_file_name(Self::BACKUP_FILE_NAME))?;
+
+		self.templates = content.templates;
+		self.templates.resolve(&self.cache_dir);
 		self.changes.print_all();
 
 		Ok(())
 	}
 
+	/// Upgrade a file written by an older version, a copy of the original file
+	/// is kept as `store.json.v<version>.bak`
+	fn migrate(path: &Path, cache_dir: &Path) -> Result<()> {
+		let Some(version) = Self::read_version(path)? else {
+			return Ok(());
+		};
+
+		if version > STORE_VERSION {
+			anyhow::bail!(newer_version_msg(version));
+		}
+
+		if version == STORE_VERSION {
+			return Ok(());
+		}
+
+		let backup_path = path.with_extension(format!("json.v{}.bak", version));
+		fs::copy(path, &backup_path)?;
+		StoreContent::update(path, |content| {
+			content.templates.relativize(cache_dir);
+			content.templates.relativize_foreign();
+			if version < 3 {
+				content.templates.refresh_sub_templates(cache_dir);
+			}
+		})?;
+
+		debug!(
+			"migrated `{}` from version {} to {}, backup: {:?}",
+			Self::FILE_NAME,
+			version,
+			STORE_VERSION,
+			backup_path
+		);
+
+		Ok(())
+	}
+
+	/// The schema version of the file, `None` if it does not exist or is empty
+	fn read_version(path: &Path) -> Result<Option<u64>> {
+		if !path.exists() {
+			return Ok(None);
+		}
+
+		let content = fs::read_to_string(path)?;
+
+		if content.is_empty() {
+			return Ok(None);
+		}
+
+		let value: Value =
+			serde_json::from_str(&content).with_context(|| {
+				format!("Failed to parse `{}`", path.to_string_lossy())
+			})?;
+
+		Ok(Some(schema_version(&value)))
+	}
+
+	/// Fails if the store was written by a newer version, a store that can
+	/// not be parsed is not an error here
+	pub fn check_version(scafalra_dir: &Path) -> Result<()> {
+		let path = scafalra_dir.join(Self::FILE_NAME);
+
+		if let Ok(Some(version)) = Self::read_version(&path) {
+			if version > STORE_VERSION {
+				anyhow::bail!(newer_version_msg(version));
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Move `store.json` aside as `store.json.broken`, returns the new path
+	pub fn set_aside(scafalra_dir: &Path) -> Result<Option<PathBuf>> {
+		let path = scafalra_dir.join(Self::FILE_NAME);
+
+		if !path.exists() {
+			return Ok(None);
+		}
+
+		let broken_path = path.with_extension("json.broken");
+		fs::rename(&path, &broken_path)?;
+
+		Ok(Some(broken_path))
+	}
+
+	/// Replace `store.json` with the backup written by the last successful
+	/// save, returns false if there is no usable backup
+	pub fn restore_backup(scafalra_dir: &Path) -> Result<bool> {
+		let backup_path = scafalra_dir.join(Self::BACKUP_FILE_NAME);
+
+		if !backup_path.exists() {
+			return Ok(false);
+		}
+
+		let Ok(content) = StoreContent::load(&backup_path) else {
+			return Ok(false);
+		};
+
+		content.save(&scafalra_dir.join(Self::FILE_NAME))?;
+
+		Ok(true)
+	}
+
+	/// Remove the templates whose directory no longer exists, returns their
+	/// names
+	pub fn prune_missing(&mut self) -> Vec<String> {
+		let names = self
+			.templates
+			.values()
+			.filter(|template| !template.path.exists())
+			.map(|template| template.name.clone())
+			.collect::<Vec<_>>();
+
+		for name in &names {
+			self.changes.push_remove(name);
+			self.ops.push(Op::Remove(name.clone()));
+			self.templates.remove(name);
+		}
+
+		names
+	}
+
 	pub fn add(&mut self, template: Template) {
 		let name = &template.name;
 
@@ -104,41 +461,55 @@ impl Store {
 		}
 
 		self.changes.push_add(name);
+		self.ops.push(Op::Add(Box::new(template.clone())));
 		self.templates.insert(name.to_string(), template);
 	}
 
 	pub fn remove(&mut self, name: &str) -> Result<()> {
-		if let Some(template) = self.templates.get(name) {
+		let Some(template) = self.templates.get(name) else {
+			anyhow::bail!(ScafalraError::NotFound(
+				self.similar_name_suggestion(name).to_string()
+			));
+		};
+
+		// Templates added from a subdir share the directory of the whole
+		// repository, which is kept until none of them uses it
+		let is_shared = self.templates.iter().any(|(other, tpl)| {
+			other != name
+				&& (tpl.path.starts_with(&template.path)
+					|| template.path.starts_with(&tpl.path))
+		});
+
+		if !is_shared && template.path.exists() {
 			remove_dir_all(&template.path)?;
-			self.changes.push_remove(name);
-			self.templates.remove(name);
 		}
+		self.changes.push_remove(name);
+		self.ops.push(Op::Remove(name.to_string()));
+		self.templates.remove(name);
 
 		Ok(())
 	}
 
-	pub fn rename(&mut self, name: &str, new_name: &str) -> bool {
+	pub fn rename(&mut self, name: &str, new_name: &str) -> Result<()> {
 		if self.templates.contains_key(new_name) {
-			println!("`{}` already exists", new_name);
-			return false;
+			anyhow::bail!(ScafalraError::Conflict(format!(
+				"`{}` already exists",
+				new_name
+			)));
 		}
 
-		let ret = match self.templates.remove(name) {
-			Some(template) => {
-				self.templates.insert(new_name.to_string(), template);
-				self.changes.push_remove(name).push_add(new_name);
-
-				true
-			}
-			None => {
-				let suggestion = self.similar_name_suggestion(name);
-				println!("{}", suggestion);
-
-				false
-			}
+		let Some(template) = self.templates.remove(name) else {
+			anyhow::bail!(ScafalraError::NotFound(
+				self.similar_name_suggestion(name).to_string()
+			));
 		};
 
-		ret
+		self.templates.insert(new_name.to_string(), template);
+		self.changes.push_remove(name).push_add(new_name);
+		self.ops
+			.push(Op::Rename(name.to_string(), new_name.to_string()));
+
+		Ok(())
 	}
 
 	pub fn print_grid(&self) -> Option<String> {
@@ -185,15 +556,50 @@ impl Store {
 		self.templates.get(name)
 	}
 
+	pub fn get_key_value(&self, name: &str) -> Option<(&str, &Template)> {
+		self.templates
+			.get_key_value(name)
+			.map(|(name, template)| (name.as_str(), template))
+	}
+
 	pub fn similar_name_suggestion<'a: 'b, 'b>(
 		&'a self,
 		target: &'a str,
 	) -> Suggestion<'b> {
+		Suggestion::new(
+			"template",
+			target,
+			self.templates.keys().map(String::as_str),
+		)
+	}
+
+	/// The templates and their names in order
+	pub fn iter(&self) -> impl Iterator<Item = (&String, &Template)> {
+		self.templates.iter()
+	}
+
+	pub fn all_templates_name(&self) -> Vec<&String> {
+		self.templates.values().map(|v| &v.name).collect()
+	}
+}
+
+pub struct Suggestion<'a> {
+	/// What is looked for, like `template`
+	pub noun: &'static str,
+	pub target: &'a str,
+	pub similar: Option<&'a str>,
+}
+
+impl<'a> Suggestion<'a> {
+	/// The most similar one of `names`, if any is similar enough
+	pub fn new<I>(noun: &'static str, target: &'a str, names: I) -> Self
+	where
+		I: IntoIterator<Item = &'a str>,
+	{
 		use strsim::normalized_levenshtein;
 
-		let similar = self
-			.templates
-			.keys()
+		let similar = names
+			.into_iter()
 			.filter_map(|name| {
 				let score = normalized_levenshtein(target, name).abs();
 				if score > 0.5 {
@@ -202,30 +608,25 @@ impl Store {
 				None
 			})
 			.max_by(|x, y| x.1.total_cmp(&y.1))
-			.map(|v| v.0.as_str());
+			.map(|v| v.0);
 
-		Suggestion {
+		Self {
+			noun,
 			target,
 			similar,
 		}
 	}
-
-	pub fn all_templates_name(&self) -> Vec<&String> {
-		self.templates.values().map(|v| &v.name).collect()
-	}
-}
-
-pub struct Suggestion<'a> {
-	pub target: &'a str,
-	pub similar: Option<&'a str>,
 }
 
 impl std::fmt::Display for Suggestion<'_> {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		let mut msg = format!("No such template `{}`", self.target);
+		let mut msg = format!("No such {} `{}`", self.noun, self.target);
 
 		if let Some(similar) = self.similar {
-			msg.push_str(&format!("\nA similar template is `{}`", similar));
+			msg.push_str(&format!(
+				"\nA similar {} is `{}`",
+				self.noun, similar
+			));
 		}
 
 		write!(f, "{}", msg)
@@ -234,39 +635,94 @@ impl std::fmt::Display for Suggestion<'_> {
 
 #[cfg(test)]
 pub mod test_utils {
-	use std::{collections::BTreeMap, fs, path::Path};
+	use std::{
+		collections::BTreeMap,
+		fs,
+		path::{Path, PathBuf},
+	};
 
 	use tempfile::{tempdir, TempDir};
 
-	use super::{Store, Template, TemplateMap};
-	use crate::sub_template::test_utils::sub_tempaltes_dir_setup;
+	use super::{Store, StoreContent, Template, TemplateMap, STORE_VERSION};
+	use crate::{
+		source::Source, sub_template::test_utils::sub_tempaltes_dir_setup,
+	};
 
 	pub struct StoreJsonMock {
 		data: Vec<Template>,
+		commit: Option<String>,
+		source: Option<Source>,
+		cache_dir: Option<PathBuf>,
 	}
 
 	impl StoreJsonMock {
 		pub fn new() -> Self {
 			Self {
 				data: Vec::new(),
+				commit: None,
+				source: None,
+				cache_dir: None,
 			}
 		}
 
+		/// Store the paths relative to `cache_dir` like `Store::save`
+		pub fn cache_dir(&mut self, cache_dir: &Path) -> &mut Self {
+			self.cache_dir = Some(cache_dir.to_path_buf());
+
+			self
+		}
+
+		/// Set the commit of templates pushed afterwards
+		pub fn commit(&mut self, commit: &str) -> &mut Self {
+			self.commit = Some(commit.to_string());
+
+			self
+		}
+
+		/// Set the source of templates pushed afterwards
+		pub fn source(&mut self, source: Source) -> &mut Self {
+			self.source = Some(source);
+
+			self
+		}
+
 		pub fn push(&mut self, name: &str, path: &Path) -> &mut Self {
-			self.data.push(Template::new(name, "url", path));
+			self.data.push(
+				Template::new(name, "url", path)
+					.with_commit(self.commit.clone())
+					.with_source(self.source.clone()),
+			);
 
 			self
 		}
 
-		pub fn build(&self) -> String {
-			let tempalte_map = TemplateMap(BTreeMap::from_iter(
+		fn template_map(&self) -> TemplateMap {
+			let mut templates = TemplateMap(BTreeMap::from_iter(
 				self.data
 					.clone()
 					.into_iter()
 					.map(|ele| (ele.name.clone(), ele)),
 			));
 
-			serde_json::to_string_pretty(&tempalte_map).unwrap()
+			if let Some(cache_dir) = &self.cache_dir {
+				templates.relativize(cache_dir);
+			}
+
+			templates
+		}
+
+		pub fn build(&self) -> String {
+			let content = StoreContent {
+				version: STORE_VERSION,
+				templates: self.template_map(),
+			};
+
+			serde_json::to_string_pretty(&content).unwrap()
+		}
+
+		/// The bare map of templates before the store was versioned
+		pub fn build_v0(&self) -> String {
+			serde_json::to_string_pretty(&self.template_map()).unwrap()
 		}
 	}
 
@@ -276,6 +732,10 @@ pub mod test_utils {
 		pub fn build(name: &str) -> Template {
 			Template::new(name, "url", "path")
 		}
+
+		pub fn build_with_path(name: &str, path: &Path) -> Template {
+			Template::new(name, "url", path)
+		}
 	}
 
 	pub struct StoreMock {
@@ -286,7 +746,7 @@ pub mod test_utils {
 	impl StoreMock {
 		pub fn with_no_content() -> Self {
 			let tmp_dir = tempdir().unwrap();
-			let store = Store::new(tmp_dir.path()).unwrap();
+			let store = Store::new(tmp_dir.path(), tmp_dir.path()).unwrap();
 
 			Self {
 				tmp_dir,
@@ -306,7 +766,7 @@ pub mod test_utils {
 			)
 			.unwrap();
 
-			let store = Store::new(tmp_dir_path).unwrap();
+			let store = Store::new(tmp_dir_path, tmp_dir_path).unwrap();
 
 			Self {
 				tmp_dir,
@@ -346,7 +806,7 @@ pub mod test_utils {
 			)
 			.unwrap();
 
-			let store = Store::new(tmp_dir_path).unwrap();
+			let store = Store::new(tmp_dir_path, tmp_dir_path).unwrap();
 
 			Self {
 				tmp_dir,
@@ -358,13 +818,18 @@ pub mod test_utils {
 
 #[cfg(test)]
 mod tests {
-	use std::fs;
+	use std::{fs, path::Path, thread};
 
 	use anyhow::Result;
 	use similar_asserts::assert_eq;
+	use tempfile::tempdir;
 	use test_case::test_case;
 
-	use super::test_utils::{StoreMock, TemplateMock};
+	use super::{
+		test_utils::{StoreJsonMock, StoreMock, TemplateMock},
+		Store,
+	};
+	use crate::{error::exit_code, path_ext::*};
 
 	#[test]
 	fn test_store_new_file_not_exists() {
@@ -392,7 +857,7 @@ mod tests {
 	fn test_store_save() -> Result<()> {
 		let StoreMock {
 			tmp_dir: _tmp_dir,
-			store,
+			mut store,
 		} = StoreMock::with_default_content();
 
 		fs::write(&store.path, "")?;
@@ -406,6 +871,230 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_store_save_concurrently() -> Result<()> {
+		let StoreMock {
+			tmp_dir,
+			store: _store,
+		} = StoreMock::with_no_content();
+
+		let handles = (0..8)
+			.map(|idx| {
+				let dir = tmp_dir.path().to_path_buf();
+
+				thread::spawn(move || -> Result<()> {
+					let mut store = Store::new(&dir, &dir)?;
+					store.add(TemplateMock::build(&format!("foo{}", idx)));
+					store.save()
+				})
+			})
+			.collect::<Vec<_>>();
+
+		for handle in handles {
+			handle.join().unwrap()?;
+		}
+
+		let store = Store::new(tmp_dir.path(), tmp_dir.path())?;
+
+		assert_eq!(store.templates.len(), 8);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_store_migrate_v0() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let store_path = tmp_dir.path().join(Store::FILE_NAME);
+		let foo_path = tmp_dir.path().join("foo");
+		let v0_content = StoreJsonMock::new().push("foo", &foo_path).build_v0();
+
+		fs::write(&store_path, &v0_content)?;
+
+		let store = Store::new(tmp_dir.path(), tmp_dir.path())?;
+
+		assert!(store.templates.contains_key("foo"));
+		assert_eq!(
+			fs::read_to_string(&store_path)?,
+			StoreJsonMock::new()
+				.cache_dir(tmp_dir.path())
+				.push("foo", &foo_path)
+				.build()
+		);
+		assert_eq!(
+			fs::read_to_string(tmp_dir.path().join("store.json.v0.bak"))?,
+			v0_content
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_store_migrate_v2() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let store_path = tmp_dir.path().join(Store::FILE_NAME);
+		let cache_dir = tmp_dir.path().join("cache");
+		let foo_dir = cache_dir.join_slash("foo/bar");
+		fs::create_dir_all(foo_dir.join_slash(".scafalra/backend"))?;
+
+		// Nested sub templates were not recorded by version 2
+		let v2_content = StoreJsonMock::new()
+			.cache_dir(&cache_dir)
+			.push("foo", &foo_dir)
+			.build()
+			.replace("\"version\": 3", "\"version\": 2");
+		fs::create_dir_all(
+			foo_dir.join_slash(".scafalra/backend/.scafalra/postgres"),
+		)?;
+		fs::write(&store_path, v2_content)?;
+
+		let store = Store::new(tmp_dir.path(), &cache_dir)?;
+		let sub_templates = &store.get("foo").unwrap().sub_templates;
+
+		assert_eq!(
+			sub_templates
+				.iter()
+				.map(|sub_tpl| sub_tpl.name.as_str())
+				.collect::<Vec<_>>(),
+			vec!["backend", "backend.postgres"]
+		);
+		assert_eq!(
+			sub_templates[1].path,
+			foo_dir.join_slash(".scafalra/backend/.scafalra/postgres")
+		);
+		assert!(tmp_dir.path().join("store.json.v2.bak").exists());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_store_migrate_foreign_paths() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let store_path = tmp_dir.path().join(Store::FILE_NAME);
+		let cache_dir = tmp_dir.path().join("cache");
+		// Another `cache` directory is in the path of the scafalra directory
+		let other_root = tmp_dir.path().join_slash("other/cache/scafalra");
+		let v0_content = StoreJsonMock::new()
+			.push("foo", &other_root.join_iter(["cache", "foo", "bar"]))
+			.push("baz", &other_root.join_iter(["cache", "foo", "bar", "baz"]))
+			.build_v0();
+
+		fs::write(&store_path, &v0_content)?;
+
+		let store = Store::new(tmp_dir.path(), &cache_dir)?;
+
+		assert_eq!(
+			store.get("foo").unwrap().path,
+			cache_dir.join_slash("foo/bar")
+		);
+		assert_eq!(
+			store.get("baz").unwrap().path,
+			cache_dir.join_slash("foo/bar/baz")
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_store_windows_paths() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let store_path = tmp_dir.path().join(Store::FILE_NAME);
+		let cache_dir = tmp_dir.path().join("cache");
+
+		// Written by scafalra on Windows
+		fs::write(
+			&store_path,
+			StoreJsonMock::new()
+				.push("foo", Path::new(r"foo\bar"))
+				.build(),
+		)?;
+
+		let store = Store::new(tmp_dir.path(), &cache_dir)?;
+
+		assert_eq!(
+			store.get("foo").unwrap().path,
+			cache_dir.join_slash("foo/bar")
+		);
+
+		fs::write(
+			&store_path,
+			StoreJsonMock::new()
+				.push(
+					"baz",
+					Path::new(
+						r"C:\Users\foo\AppData\Roaming\scafalra\config\cache\foo\baz",
+					),
+				)
+				.build_v0(),
+		)?;
+
+		let mut store = Store::new(tmp_dir.path(), &cache_dir)?;
+
+		assert_eq!(
+			store.get("baz").unwrap().path,
+			cache_dir.join_slash("foo/baz")
+		);
+
+		// Saved with `/` on every platform
+		store.add(TemplateMock::build_with_path(
+			"qux",
+			&cache_dir.join_iter(["foo", "qux"]),
+		));
+		store.save()?;
+
+		assert!(
+			fs::read_to_string(&store_path)?.contains("\"path\": \"foo/qux\"")
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_store_relative_paths() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let dir_a = tmp_dir.path().join("a");
+		let dir_b = tmp_dir.path().join("b");
+		fs::create_dir_all(&dir_a)?;
+		fs::create_dir_all(&dir_b)?;
+
+		let mut store = Store::new(&dir_a, &dir_a.join("cache"))?;
+		store.add(TemplateMock::build_with_path(
+			"foo",
+			&dir_a.join("cache").join("foo"),
+		));
+		store.save()?;
+
+		let content = fs::read_to_string(dir_a.join(Store::FILE_NAME))?;
+		assert!(content.contains("\"path\": \"foo\""));
+
+		fs::write(dir_b.join(Store::FILE_NAME), content)?;
+
+		let store = Store::new(&dir_b, &dir_b.join("cache"))?;
+		assert_eq!(
+			store.get("foo").unwrap().path,
+			dir_b.join("cache").join("foo")
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_store_newer_version() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let store_path = tmp_dir.path().join(Store::FILE_NAME);
+		let content = "{\n  \"version\": 99,\n  \"templates\": {}\n}";
+
+		fs::write(&store_path, content)?;
+
+		let Err(err) = Store::new(tmp_dir.path(), tmp_dir.path()) else {
+			panic!("a newer store should not be loaded");
+		};
+
+		assert!(format!("{:#}", err).contains("newer version"));
+		assert_eq!(fs::read_to_string(&store_path)?, content);
+
+		Ok(())
+	}
+
 	#[test]
 	fn test_store_add() {
 		let StoreMock {
@@ -450,6 +1139,37 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_store_remove_shared() -> Result<()> {
+		let StoreMock {
+			tmp_dir,
+			mut store,
+		} = StoreMock::with_default_content();
+
+		let foo_dir = tmp_dir.path().join("foo");
+		let baz_dir = foo_dir.join("baz");
+		fs::create_dir(&baz_dir)?;
+		store.add(TemplateMock::build_with_path("baz", &baz_dir));
+
+		// The files of `baz` belong to `foo`
+		store.remove("baz")?;
+
+		assert!(baz_dir.exists());
+		assert!(!store.templates.contains_key("baz"));
+
+		store.add(TemplateMock::build_with_path("baz", &baz_dir));
+		store.remove("foo")?;
+
+		assert!(baz_dir.exists());
+		assert!(!store.templates.contains_key("foo"));
+
+		store.remove("baz")?;
+
+		assert!(!baz_dir.exists());
+
+		Ok(())
+	}
+
 	#[test]
 	fn test_store_remove_not_found() -> Result<()> {
 		let StoreMock {
@@ -457,38 +1177,42 @@ mod tests {
 			mut store,
 		} = StoreMock::with_default_content();
 
-		store.remove("bar")?;
+		let err = store.remove("bar").unwrap_err();
 
+		assert_eq!(exit_code(&err), 3);
 		assert_eq!(store.changes.inner.len(), 0);
 
 		Ok(())
 	}
 
 	#[test]
-	fn test_store_rename() {
+	fn test_store_rename() -> Result<()> {
 		let StoreMock {
 			tmp_dir: _tmp_dir,
 			mut store,
 		} = StoreMock::with_default_content();
 
-		store.rename("foo", "bar");
+		store.rename("foo", "bar")?;
 
 		assert_eq!(store.templates.len(), 1);
 		assert!(!store.templates.contains_key("foo"));
 		assert!(store.templates.contains_key("bar"));
 		assert_eq!(store.changes.inner, vec!["- foo", "+ bar"]);
+
+		Ok(())
 	}
 
-	#[test_case("foo"; "exists")]
-	#[test_case("bar"; "not found")]
-	fn test_store_bad_rename(name: &str) {
+	#[test_case("foo", "foo", 7; "exists")]
+	#[test_case("bar", "baz", 3; "not found")]
+	fn test_store_bad_rename(name: &str, new_name: &str, code: i32) {
 		let StoreMock {
 			tmp_dir: _tmp_dir,
 			mut store,
 		} = StoreMock::with_default_content();
 
-		store.rename(name, "foo");
+		let err = store.rename(name, new_name).unwrap_err();
 
+		assert_eq!(exit_code(&err), code);
 		assert_eq!(store.templates.len(), 1);
 		assert!(store.templates.contains_key("foo"));
 	}
@@ -1,14 +1,25 @@
 use std::{
+	collections::BTreeMap,
 	fs,
 	path::{Path, PathBuf},
 };
 
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+	error::ScafalraError,
+	manifest::{Manifest, SubTemplateMeta},
+	store::Suggestion,
+};
+
 #[derive(Clone, Deserialize, Serialize)]
 pub struct SubTemplate {
 	pub name: String,
 	pub path: PathBuf,
+	/// The name of the sub template it is nested in
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub parent: Option<String>,
 }
 
 impl SubTemplate {
@@ -19,6 +30,7 @@ impl SubTemplate {
 					return Some(Self {
 						name: name.to_string(),
 						path: path.to_path_buf(),
+						parent: None,
 					});
 				}
 			}
@@ -26,23 +38,180 @@ impl SubTemplate {
 
 		None
 	}
+
+	/// The name of its directory, without the name of the parent
+	pub fn dir_name(&self) -> &str {
+		self.parent
+			.as_deref()
+			.and_then(|parent| self.name.strip_prefix(parent))
+			.and_then(|name| name.strip_prefix('.'))
+			.unwrap_or(&self.name)
+	}
 }
 
 pub const SUB_TEMPLATE_DIR: &str = ".scafalra";
 
+/// Sub templates may contain their own `.scafalra` directory, the names of
+/// nested sub templates are joined with dots, like `backend.postgres`. A
+/// parent is placed before the sub templates nested in it
 pub fn read_sub_templates(template_path: &Path) -> Vec<SubTemplate> {
-	match fs::read_dir(template_path.join(SUB_TEMPLATE_DIR)) {
-		Ok(entries) => {
-			let mut vs = Vec::new();
-			for entry in entries.filter_map(|e| e.ok()) {
-				if let Some(sub_tpl) = SubTemplate::new(&entry.path()) {
-					vs.push(sub_tpl);
-				}
+	let mut vs = Vec::new();
+	collect_sub_templates(template_path, None, &mut vs);
+
+	vs
+}
+
+fn collect_sub_templates(
+	dir: &Path,
+	parent: Option<&str>,
+	vs: &mut Vec<SubTemplate>,
+) {
+	let Ok(entries) = fs::read_dir(dir.join(SUB_TEMPLATE_DIR)) else {
+		return;
+	};
+
+	for entry in entries.filter_map(|e| e.ok()) {
+		if let Some(mut sub_tpl) = SubTemplate::new(&entry.path()) {
+			if let Some(parent) = parent {
+				sub_tpl.name = format!("{}.{}", parent, sub_tpl.name);
+				sub_tpl.parent = Some(parent.to_string());
+			}
+
+			let name = sub_tpl.name.clone();
+			let path = sub_tpl.path.clone();
+
+			vs.push(sub_tpl);
+			collect_sub_templates(&path, Some(&name), vs);
+		}
+	}
+}
+
+/// The metadata of every sub template keyed by its dotted name, the metadata
+/// of a nested sub template is read from the manifest of its parent. Targets
+/// are resolved relative to the destination, names in `requires` and
+/// `conflicts` are made dotted, and a nested sub template requires its parent
+pub fn read_sub_template_metas(
+	template_path: &Path,
+	sub_templates: &[SubTemplate],
+) -> Result<BTreeMap<String, SubTemplateMeta>> {
+	let mut metas: BTreeMap<String, SubTemplateMeta> = BTreeMap::new();
+
+	// `read_sub_templates` places the parents first
+	for sub_tpl in sub_templates {
+		let parent = sub_tpl.parent.as_deref();
+		let name = sub_tpl.dir_name();
+
+		let parent_dir = match parent {
+			Some(parent) => {
+				let Some(parent) =
+					sub_templates.iter().find(|sub_tpl| sub_tpl.name == parent)
+				else {
+					continue;
+				};
+				&parent.path
+			}
+			None => template_path,
+		};
+
+		let mut meta = Manifest::load(parent_dir)?.sub_template(name);
+		let parent_target = parent
+			.and_then(|parent| metas.get(parent))
+			.and_then(|meta| meta.target.clone())
+			.unwrap_or_default();
+		let prefix = parent
+			.map(|parent| format!("{}.", parent))
+			.unwrap_or_default();
+		let dotted = |names: &[String]| {
+			names
+				.iter()
+				.map(|name| format!("{}{}", prefix, name))
+				.collect::<Vec<_>>()
+		};
+
+		meta.target = Some(parent_target.join(meta.target(name)?));
+		meta.merge = false;
+		meta.requires = dotted(&meta.requires);
+		meta.requires.extend(parent.map(String::from));
+		meta.conflicts = dotted(&meta.conflicts);
+
+		metas.insert(sub_tpl.name.clone(), meta);
+	}
+
+	Ok(metas)
+}
+
+/// Add the sub templates required by the selected ones, a sub template is
+/// created after the ones it requires, and check the exclusions
+pub fn resolve_selection(
+	sub_templates: &[SubTemplate],
+	selected: &[String],
+	metas: &BTreeMap<String, SubTemplateMeta>,
+) -> Result<Vec<String>> {
+	fn visit(
+		name: &str,
+		sub_templates: &[SubTemplate],
+		metas: &BTreeMap<String, SubTemplateMeta>,
+		resolved: &mut Vec<String>,
+	) -> Result<()> {
+		if resolved.iter().any(|resolved| resolved == name) {
+			return Ok(());
+		}
+
+		// Added before its requirements, so that a cycle ends here
+		resolved.push(name.to_string());
+		let pos = resolved.len() - 1;
+
+		for required in
+			metas.get(name).map(|m| &m.requires).into_iter().flatten()
+		{
+			if !sub_templates
+				.iter()
+				.any(|sub_tpl| &sub_tpl.name == required)
+			{
+				anyhow::bail!(ScafalraError::NotFound(format!(
+					"Sub template `{}` required by `{}` does not exist",
+					required, name
+				)));
+			}
+
+			visit(required, sub_templates, metas, resolved)?;
+		}
+
+		let name = resolved.remove(pos);
+		resolved.push(name);
+
+		Ok(())
+	}
+
+	let mut resolved = Vec::new();
+
+	for name in selected {
+		if !sub_templates.iter().any(|sub_tpl| &sub_tpl.name == name) {
+			let suggestion = Suggestion::new(
+				"sub template",
+				name,
+				sub_templates.iter().map(|sub_tpl| sub_tpl.name.as_str()),
+			);
+			anyhow::bail!(ScafalraError::NotFound(suggestion.to_string()));
+		}
+
+		visit(name, sub_templates, metas, &mut resolved)?;
+	}
+
+	for name in &resolved {
+		for conflict in
+			metas.get(name).map(|m| &m.conflicts).into_iter().flatten()
+		{
+			if resolved.contains(conflict) {
+				anyhow::bail!(ScafalraError::Usage(format!(
+					"Sub templates `{}` and `{}` can not be created together",
+					name, conflict
+				)));
 			}
-			vs
 		}
-		_ => Vec::with_capacity(0),
 	}
+
+	Ok(resolved)
 }
 
 #[cfg(test)]
@@ -60,3 +229,170 @@ pub mod test_utils {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		collections::BTreeMap,
+		path::{Path, PathBuf},
+	};
+
+	use anyhow::Result;
+	use tempfile::tempdir;
+
+	use super::{
+		read_sub_template_metas, read_sub_templates, resolve_selection,
+		test_utils::sub_tempaltes_dir_setup, SubTemplate,
+	};
+	use crate::{
+		error::ScafalraError,
+		manifest::{test_utils::manifest_setup, SubTemplateMeta},
+	};
+
+	fn sub_templates(names: &[&str]) -> Vec<SubTemplate> {
+		names
+			.iter()
+			.map(|name| {
+				SubTemplate {
+					name: name.to_string(),
+					path: PathBuf::from(name),
+					parent: None,
+				}
+			})
+			.collect()
+	}
+
+	fn strings(names: &[&str]) -> Vec<String> {
+		names.iter().map(|name| name.to_string()).collect()
+	}
+
+	#[test]
+	fn test_read_nested_sub_templates() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let tpl_dir = tmp_dir.path();
+		sub_tempaltes_dir_setup(tpl_dir, &["backend"]);
+		let backend_dir = tpl_dir.join(".scafalra").join("backend");
+		sub_tempaltes_dir_setup(&backend_dir, &["postgres", "sqlite"]);
+		manifest_setup(tpl_dir, "[sub_templates.backend]\ntarget = \"server\"");
+		manifest_setup(
+			&backend_dir,
+			"[sub_templates.postgres]\nconflicts = [\"sqlite\"]\n\n[sub_templates.sqlite]\nmerge = true",
+		);
+
+		let sub_tpls = read_sub_templates(tpl_dir);
+		let mut names =
+			sub_tpls.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+		names.sort_unstable();
+
+		assert_eq!(
+			names,
+			vec!["backend", "backend.postgres", "backend.sqlite"]
+		);
+
+		let metas = read_sub_template_metas(tpl_dir, &sub_tpls)?;
+		let postgres = &metas["backend.postgres"];
+
+		assert_eq!(postgres.target("postgres")?, Path::new("server/postgres"));
+		assert_eq!(postgres.requires, vec!["backend"]);
+		assert_eq!(postgres.conflicts, vec!["backend.sqlite"]);
+		assert_eq!(
+			metas["backend.sqlite"].target("sqlite")?,
+			Path::new("server")
+		);
+
+		let resolved = resolve_selection(
+			&sub_tpls,
+			&["backend.postgres".to_string()],
+			&metas,
+		)?;
+		assert_eq!(resolved, vec!["backend", "backend.postgres"]);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_read_dotted_sub_templates() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let tpl_dir = tmp_dir.path();
+		sub_tempaltes_dir_setup(tpl_dir, &[".github", "node.js"]);
+		manifest_setup(
+			tpl_dir,
+			"[sub_templates.\".github\"]\ndescription = \"workflows\"\n\n[sub_templates.\"node.js\"]\ntarget = \"js\"",
+		);
+
+		let sub_tpls = read_sub_templates(tpl_dir);
+
+		assert!(sub_tpls.iter().all(|sub_tpl| sub_tpl.parent.is_none()));
+
+		let metas = read_sub_template_metas(tpl_dir, &sub_tpls)?;
+
+		assert_eq!(metas[".github"].description.as_deref(), Some("workflows"));
+		assert!(metas[".github"].requires.is_empty());
+		assert_eq!(metas["node.js"].target("node.js")?, Path::new("js"));
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_resolve_selection() {
+		let sub_tpls = sub_templates(&["ci", "lint", "fmt", "gitlab-ci"]);
+		let metas = BTreeMap::from([
+			(
+				"ci".to_string(),
+				SubTemplateMeta {
+					requires: strings(&["lint"]),
+					conflicts: strings(&["gitlab-ci"]),
+					..SubTemplateMeta::default()
+				},
+			),
+			(
+				"lint".to_string(),
+				SubTemplateMeta {
+					requires: strings(&["fmt", "ci"]),
+					..SubTemplateMeta::default()
+				},
+			),
+		]);
+
+		let resolved =
+			resolve_selection(&sub_tpls, &strings(&["ci"]), &metas).unwrap();
+		assert_eq!(resolved, vec!["fmt", "lint", "ci"]);
+
+		let err = resolve_selection(
+			&sub_tpls,
+			&strings(&["gitlab-ci", "ci"]),
+			&metas,
+		)
+		.unwrap_err();
+		assert!(matches!(err.downcast_ref(), Some(ScafalraError::Usage(_))));
+
+		let err = resolve_selection(&sub_tpls[..1], &strings(&["ci"]), &metas)
+			.unwrap_err();
+		assert!(err.to_string().contains("`lint` required by `ci`"));
+	}
+
+	#[test]
+	fn test_resolve_selection_unknown() {
+		let sub_tpls = sub_templates(&["gitlab-ci", "lint"]);
+
+		let err = resolve_selection(
+			&sub_tpls,
+			&strings(&["gitlab-cli"]),
+			&BTreeMap::new(),
+		)
+		.unwrap_err();
+		assert!(matches!(
+			err.downcast_ref(),
+			Some(ScafalraError::NotFound(_))
+		));
+		assert_eq!(
+			err.to_string(),
+			"No such sub template `gitlab-cli`\nA similar sub template is `gitlab-ci`"
+		);
+
+		let err =
+			resolve_selection(&sub_tpls, &strings(&["foo"]), &BTreeMap::new())
+				.unwrap_err();
+		assert_eq!(err.to_string(), "No such sub template `foo`");
+	}
+}
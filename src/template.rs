@@ -3,7 +3,10 @@ use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
 use tabled::Tabled;
 
-use crate::sub_template::{read_sub_templates, SubTemplate};
+use crate::{
+	source::Source,
+	sub_template::{read_sub_templates, SubTemplate},
+};
 
 #[derive(Deserialize, Serialize, Clone, Tabled)]
 pub struct Template {
@@ -13,6 +16,9 @@ pub struct Template {
 	pub url: String,
 	#[tabled(skip)]
 	pub path: PathBuf,
+	#[tabled(skip)]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub commit: Option<String>,
 	#[tabled(rename = "created at", order = 3)]
 	pub created_at: String,
 	#[tabled(
@@ -21,6 +27,9 @@ pub struct Template {
 		display_with = "display_sub_templates"
 	)]
 	pub sub_templates: Vec<SubTemplate>,
+	#[tabled(skip)]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub source: Option<Source>,
 }
 
 impl Template {
@@ -44,8 +53,36 @@ impl Template {
 			name: String::from(name.as_ref()),
 			url: String::from(url.as_ref()),
 			path,
+			commit: None,
 			created_at,
 			sub_templates,
+			source: None,
+		}
+	}
+
+	pub fn with_commit(self, commit: Option<String>) -> Self {
+		Self {
+			commit,
+			..self
+		}
+	}
+
+	pub fn with_source(self, source: Option<Source>) -> Self {
+		Self {
+			source,
+			..self
+		}
+	}
+
+	/// Apply `f` to the paths of the template and its sub templates
+	pub fn map_paths<F>(&mut self, f: F)
+	where
+		F: Fn(&Path) -> PathBuf,
+	{
+		self.path = f(&self.path);
+
+		for sub_tpl in &mut self.sub_templates {
+			sub_tpl.path = f(&sub_tpl.path);
 		}
 	}
 }
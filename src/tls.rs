@@ -0,0 +1,123 @@
+use std::{error::Error, fs, io, path::Path, sync::Arc};
+
+use anyhow::Result;
+use rustls::{ClientConfig, RootCertStore};
+
+use crate::{debug, error::ScafalraError};
+
+/// Build a TLS config that trusts the bundled roots, the certificates in
+/// `ca_bundles` and optionally the system certificate store
+pub fn client_config<P: AsRef<Path>>(
+	ca_bundles: &[P],
+	system_certs: bool,
+) -> Result<Arc<ClientConfig>> {
+	let mut roots = RootCertStore::empty();
+	roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+	if system_certs {
+		let certs =
+			rustls_native_certs::load_native_certs().map_err(|err| {
+				ScafalraError::Filesystem(format!(
+					"Failed to load the system certificate store: {}",
+					err
+				))
+			})?;
+		let (added, ignored) = roots.add_parsable_certificates(certs);
+
+		debug!("system certs: {} added, {} ignored", added, ignored);
+	}
+
+	for path in ca_bundles {
+		let path = path.as_ref();
+		let err = |err: &dyn std::fmt::Display| {
+			ScafalraError::Filesystem(format!(
+				"Failed to load CA bundle `{}`: {}",
+				path.to_string_lossy(),
+				err
+			))
+		};
+
+		let content = fs::read(path).map_err(|e| err(&e))?;
+		let certs = rustls_pemfile::certs(&mut content.as_slice())
+			.collect::<io::Result<Vec<_>>>()
+			.map_err(|e| err(&e))?;
+
+		if certs.is_empty() {
+			anyhow::bail!(err(&"no PEM certificates found"));
+		}
+
+		for cert in certs {
+			roots.add(cert).map_err(|e| err(&e))?;
+		}
+	}
+
+	let config = ClientConfig::builder()
+		.with_root_certificates(roots)
+		.with_no_client_auth();
+
+	Ok(Arc::new(config))
+}
+
+/// Whether the server certificate could not be verified
+pub fn is_cert_error(err: &(dyn Error + 'static)) -> bool {
+	let mut source = Some(err);
+
+	while let Some(err) = source {
+		if let Some(rustls::Error::InvalidCertificate(_)) = err.downcast_ref() {
+			return true;
+		}
+
+		// `io::Error` does not expose the wrapped error as its source
+		if let Some(inner) = err
+			.downcast_ref::<io::Error>()
+			.and_then(|err| err.get_ref())
+		{
+			if is_cert_error(inner) {
+				return true;
+			}
+		}
+
+		source = err.source();
+	}
+
+	false
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{fs, io};
+
+	use anyhow::Result;
+	use tempfile::tempdir;
+
+	use super::{client_config, is_cert_error};
+	use crate::error::ScafalraError;
+
+	#[test]
+	fn test_client_config_bad_bundle() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let bundle = tmp_dir.path().join("ca.pem");
+		fs::write(&bundle, "foo")?;
+
+		let err = client_config(&[&bundle], false).unwrap_err();
+		let Some(ScafalraError::Filesystem(msg)) = err.downcast_ref() else {
+			panic!("unexpected error: {:?}", err);
+		};
+		assert!(msg.contains("ca.pem"));
+
+		assert!(client_config(&[tmp_dir.path().join("foo")], false).is_err());
+		assert!(client_config::<&str>(&[], false).is_ok());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_is_cert_error() {
+		let err = io::Error::other(rustls::Error::InvalidCertificate(
+			rustls::CertificateError::UnknownIssuer,
+		));
+		assert!(is_cert_error(&err));
+
+		assert!(!is_cert_error(&io::Error::other("foo")));
+	}
+}
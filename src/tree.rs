@@ -0,0 +1,109 @@
+use std::{fs, path::Path};
+
+use anyhow::Result;
+
+pub fn render_tree(dir: &Path, max_depth: Option<usize>) -> Result<String> {
+	let mut lines = Vec::new();
+
+	walk(dir, "", 1, max_depth, &mut lines)?;
+
+	Ok(lines.join("\n"))
+}
+
+fn walk(
+	dir: &Path,
+	prefix: &str,
+	depth: usize,
+	max_depth: Option<usize>,
+	lines: &mut Vec<String>,
+) -> Result<()> {
+	let mut entries = fs::read_dir(dir)?
+		.filter_map(|entry| entry.ok().map(|e| e.path()))
+		.collect::<Vec<_>>();
+
+	entries.sort_unstable();
+
+	let len = entries.len();
+
+	for (idx, entry_path) in entries.into_iter().enumerate() {
+		let is_last = idx + 1 == len;
+		let Some(file_name) = entry_path.file_name() else {
+			continue;
+		};
+		let mut name = file_name.to_string_lossy().to_string();
+		let is_dir = entry_path.is_dir();
+
+		if is_dir {
+			name.push('/');
+		}
+
+		let branch = if is_last {
+			"└── "
+		} else {
+			"├── "
+		};
+
+		lines.push(format!("{}{}{}", prefix, branch, name));
+
+		if is_dir && max_depth.map_or(true, |max| depth < max) {
+			let child_prefix = if is_last {
+				format!("{}    ", prefix)
+			} else {
+				format!("{}│   ", prefix)
+			};
+			walk(&entry_path, &child_prefix, depth + 1, max_depth, lines)?;
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use anyhow::Result;
+	use similar_asserts::assert_eq;
+	use tempfile::tempdir;
+
+	use super::render_tree;
+
+	fn tree_setup(root: &std::path::Path) {
+		fs::create_dir_all(root.join("a").join("a1")).unwrap();
+		fs::write(root.join("a").join("a1").join("foo.txt"), "").unwrap();
+		fs::write(root.join("a").join("bar.txt"), "").unwrap();
+		fs::write(root.join("baz.txt"), "").unwrap();
+	}
+
+	#[test]
+	fn test_render_tree() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		tree_setup(tmp_dir.path());
+
+		assert_eq!(
+			render_tree(tmp_dir.path(), None)?,
+			concat!(
+				"├── a/\n",
+				"│   ├── a1/\n",
+				"│   │   └── foo.txt\n",
+				"│   └── bar.txt\n",
+				"└── baz.txt",
+			)
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_render_tree_max_depth() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		tree_setup(tmp_dir.path());
+
+		assert_eq!(
+			render_tree(tmp_dir.path(), Some(1))?,
+			concat!("├── a/\n", "└── baz.txt")
+		);
+
+		Ok(())
+	}
+}
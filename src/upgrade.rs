@@ -0,0 +1,238 @@
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	fs,
+	path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+use crate::plan::Plan;
+
+#[derive(Default)]
+pub struct UpgradeReport {
+	pub added: Vec<PathBuf>,
+	pub updated: Vec<PathBuf>,
+	pub removed: Vec<PathBuf>,
+	pub conflicted: Vec<PathBuf>,
+}
+
+impl UpgradeReport {
+	pub fn print(&self) -> String {
+		use crate::colorize::Colorize;
+
+		let mut lines = Vec::new();
+
+		for path in &self.added {
+			lines.push(format!("{} {}", "+".green(), path.to_string_lossy()));
+		}
+
+		for path in &self.updated {
+			lines.push(format!("{} {}", "~".blue(), path.to_string_lossy()));
+		}
+
+		for path in &self.removed {
+			lines.push(format!("{} {}", "-".red(), path.to_string_lossy()));
+		}
+
+		for path in &self.conflicted {
+			lines.push(format!("{} {}", "!".red(), path.to_string_lossy()));
+		}
+
+		lines.push(format!(
+			"{} added, {} updated, {} removed, {} conflicted",
+			self.added.len(),
+			self.updated.len(),
+			self.removed.len(),
+			self.conflicted.len()
+		));
+
+		lines.join("\n")
+	}
+}
+
+/// Apply the changes between the `base` and `new` versions of a template to
+/// `project_dir`, files that were changed on both sides are merged with
+/// conflict markers
+pub fn upgrade(
+	base: &Plan,
+	new: &Plan,
+	project_dir: &Path,
+) -> Result<UpgradeReport> {
+	let base_files = files(base);
+	let new_files = files(new);
+	let paths = base_files
+		.keys()
+		.chain(new_files.keys())
+		.collect::<BTreeSet<_>>();
+
+	let mut report = UpgradeReport::default();
+
+	for path in paths {
+		let base = read(base_files.get(path).copied())?;
+		let new = read(new_files.get(path).copied())?;
+
+		if base == new {
+			continue;
+		}
+
+		let target = project_dir.join(path);
+		let ours = read(target.is_file().then_some(&target))?;
+
+		match (base, new, ours) {
+			(None, Some(new), None) => {
+				write(&target, &new)?;
+				report.added.push(path.to_path_buf());
+			}
+			(Some(base), None, Some(ours)) => {
+				if base == ours {
+					fs::remove_file(&target)?;
+					report.removed.push(path.to_path_buf());
+				} else {
+					report.conflicted.push(path.to_path_buf());
+				}
+			}
+			(base, Some(new), Some(ours)) => {
+				if ours == new {
+					continue;
+				}
+
+				let base = base.unwrap_or_default();
+
+				if base == ours {
+					write(&target, &new)?;
+					report.updated.push(path.to_path_buf());
+					continue;
+				}
+
+				match merge(&base, &ours, &new) {
+					Some(Ok(merged)) => {
+						write(&target, merged.as_bytes())?;
+						report.updated.push(path.to_path_buf());
+					}
+					Some(Err(merged)) => {
+						write(&target, merged.as_bytes())?;
+						report.conflicted.push(path.to_path_buf());
+					}
+					None => report.conflicted.push(path.to_path_buf()),
+				}
+			}
+			// Removed on both sides, or removed from the project and not
+			// changed in the template
+			_ => (),
+		}
+	}
+
+	Ok(report)
+}
+
+fn files(plan: &Plan) -> BTreeMap<&PathBuf, &PathBuf> {
+	plan.entries()
+		.filter(|(_, entry)| !entry.is_dir)
+		.map(|(path, entry)| (path, &entry.source))
+		.collect()
+}
+
+fn read(path: Option<&PathBuf>) -> Result<Option<Vec<u8>>> {
+	Ok(match path {
+		Some(path) => Some(fs::read(path)?),
+		None => None,
+	})
+}
+
+fn write(target: &Path, content: &[u8]) -> Result<()> {
+	if let Some(parent) = target.parent() {
+		fs::create_dir_all(parent)?;
+	}
+
+	fs::write(target, content)?;
+
+	Ok(())
+}
+
+/// Returns `None` for binary files
+fn merge(
+	base: &[u8],
+	ours: &[u8],
+	theirs: &[u8],
+) -> Option<Result<String, String>> {
+	let base = std::str::from_utf8(base).ok()?;
+	let ours = std::str::from_utf8(ours).ok()?;
+	let theirs = std::str::from_utf8(theirs).ok()?;
+
+	Some(diffy::merge(base, ours, theirs))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{fs, path::Path};
+
+	use anyhow::Result;
+	use similar_asserts::assert_eq;
+	use tempfile::tempdir;
+
+	use super::upgrade;
+	use crate::plan::Plan;
+
+	#[test]
+	fn test_upgrade() -> Result<()> {
+		let tmp_dir = tempdir()?;
+		let base_dir = tmp_dir.path().join("base");
+		let new_dir = tmp_dir.path().join("new");
+		let project_dir = tmp_dir.path().join("project");
+
+		for dir in [&base_dir, &new_dir, &project_dir] {
+			fs::create_dir_all(dir)?;
+		}
+
+		let files = [
+			// (name, base, new, project)
+			("added.txt", None, Some("added"), None),
+			("removed.txt", Some("removed"), None, Some("removed")),
+			("kept.txt", Some("kept"), None, Some("kept, changed")),
+			("updated.txt", Some("a\n"), Some("b\n"), Some("a\n")),
+			(
+				"merged.txt",
+				Some("a\nb\nc\n"),
+				Some("a\nb\nC\n"),
+				Some("A\nb\nc\n"),
+			),
+			("conflicted.txt", Some("a\n"), Some("b\n"), Some("c\n")),
+			("unchanged.txt", Some("a\n"), Some("a\n"), Some("c\n")),
+		];
+
+		for (name, base, new, project) in files {
+			for (dir, content) in
+				[(&base_dir, base), (&new_dir, new), (&project_dir, project)]
+			{
+				if let Some(content) = content {
+					fs::write(dir.join(name), content)?;
+				}
+			}
+		}
+
+		let mut base = Plan::new();
+		base.add_dir(&base_dir, Path::new(""))?;
+		let mut new = Plan::new();
+		new.add_dir(&new_dir, Path::new(""))?;
+
+		let report = upgrade(&base, &new, &project_dir)?;
+
+		let read = |name: &str| fs::read_to_string(project_dir.join(name)).ok();
+
+		assert_eq!(read("added.txt").as_deref(), Some("added"));
+		assert_eq!(read("removed.txt"), None);
+		assert_eq!(read("kept.txt").as_deref(), Some("kept, changed"));
+		assert_eq!(read("updated.txt").as_deref(), Some("b\n"));
+		assert_eq!(read("merged.txt").as_deref(), Some("A\nb\nC\n"));
+		assert!(read("conflicted.txt").unwrap().contains("<<<<<<<"));
+		assert_eq!(read("unchanged.txt").as_deref(), Some("c\n"));
+		assert_eq!(report.added, [Path::new("added.txt")]);
+		assert_eq!(report.removed, [Path::new("removed.txt")]);
+		assert_eq!(
+			report.conflicted,
+			[Path::new("conflicted.txt"), Path::new("kept.txt")]
+		);
+
+		Ok(())
+	}
+}